@@ -1,6 +1,6 @@
 use parser::parse::dump_file::dump_blogs;
 
-use parser::parse::paths::{JSON, MARKDOWN};
+use parser::parse::paths::{JSON, MARKDOWN, PUBLIC};
 
 use clap::{ArgAction, Parser};
 use std::io::Error;
@@ -11,7 +11,8 @@ fn main() -> Result<(), Error> {
     env_logger::init();
     let markdown = Path::new(MARKDOWN);
     let json = Path::new(JSON);
-    dump_blogs(markdown, json, args.pretty)?;
+    let public = Path::new(PUBLIC);
+    dump_blogs(markdown, json, public, args.pretty)?;
     println!("successfully parsed blogs");
     Ok(())
 }
@@ -3,10 +3,18 @@
 //! The binary parses blogs written in ../blog.
 //! To library can be used via the `parse.to_html.parse_markdown` function.
 
+pub mod diagrams;
+
 pub mod dump_file;
 
+pub mod html;
+
 pub mod html_element;
 
+pub mod images;
+
+pub mod inline;
+
 pub mod paths;
 
-pub mod to_html;
\ No newline at end of file
+pub mod to_html;
@@ -0,0 +1,121 @@
+//! Pre-renders the diagrams of a parsed blog so that the website does not need to draw them.
+//! DOT diagrams are rendered to SVG by Graphviz when its `dot` program is installed, and are otherwise left for the website to render.
+
+use log::info;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+use super::html_element::{for_each_element, DiagramKind, HTMLElement};
+
+/// Graphviz program which renders DOT diagrams.
+const DOT: &str = "dot";
+
+/// Renders every DOT diagram in a parsed blog to SVG, which is stored alongside the diagram's source.
+/// Diagrams are left unrendered if Graphviz is not installed.
+///
+/// # Arguments
+/// * `html` - The parsed elements of the blog.
+///
+/// # Errors
+/// If Graphviz could not render a diagram, such as when its source is invalid.
+pub fn render_diagrams(html: &mut [HTMLElement]) -> Result<(), std::io::Error> {
+    render_with(html, DOT)
+}
+
+/// Render every DOT diagram with the given program, stopping at the first error.
+fn render_with(html: &mut [HTMLElement], program: &str) -> Result<(), std::io::Error> {
+    let mut result = Ok(());
+    for_each_element(html, &mut |element| {
+        if let (
+            HTMLElement::Diagram {
+                kind: DiagramKind::Dot,
+                source,
+                svg,
+            },
+            Ok(()),
+        ) = (element, &result)
+        {
+            match render_dot(program, source) {
+                Ok(rendered) => *svg = rendered,
+                Err(e) => result = Err(e),
+            }
+        }
+    });
+    result
+}
+
+/// Render the source of a DOT diagram to SVG by piping it through the program.
+/// Return `None` if the program is not installed.
+fn render_dot(program: &str, source: &str) -> Result<Option<String>, std::io::Error> {
+    let spawned = Command::new(program)
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            info!(
+                "{} is not installed so DOT diagrams are left unrendered",
+                program
+            );
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+
+    // the program can exit without reading the source, which is then reported by its exit status
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(source.as_bytes()) {
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "could not render DOT diagram: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    info!("rendered DOT diagram with {}", program);
+    String::from_utf8(output.stdout)
+        .map(Some)
+        .map_err(std::io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::to_html::parse_markdown;
+
+    /// Shorthand for a blog holding a DOT diagram and a Mermaid diagram.
+    fn diagrams() -> Vec<HTMLElement> {
+        parse_markdown(&[
+            "```dot".to_string(),
+            "digraph { main -> feature }".to_string(),
+            "```".to_string(),
+            "```mermaid".to_string(),
+            "gitGraph".to_string(),
+            "```".to_string(),
+        ])
+    }
+
+    /// Test that diagrams are left unrendered when Graphviz is not installed.
+    #[test]
+    fn test_render_without_program() {
+        let mut html = diagrams();
+        render_with(&mut html, "not-an-installed-program").expect("failed to skip diagrams");
+        assert_eq!(html, diagrams());
+    }
+
+    /// Test that rendering fails when the program cannot render a diagram.
+    #[test]
+    fn test_render_failure() {
+        let mut html = diagrams();
+        let error = render_with(&mut html, "false").expect_err("failed render was accepted");
+        assert!(error.to_string().contains("could not render DOT diagram"));
+    }
+}
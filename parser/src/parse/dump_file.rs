@@ -1,5 +1,7 @@
 //! The `dump_blogs` function contains the main code used for the binary crate.
 //! It will parse the Markdown text for all blogs in `../blog` and create a combined JSON file in `../website/src`.
+//! Images referenced by the blogs are copied into `../website/public`.
+//! DOT diagrams are rendered to SVG when Graphviz is installed.
 
 use log::info;
 use std::fs::{read_dir, read_to_string, File};
@@ -8,7 +10,9 @@ use std::path::{Path, PathBuf};
 
 use serde_json::{to_string, to_string_pretty};
 
+use super::diagrams::render_diagrams;
 use super::html_element::HTMLElement;
+use super::images::{publish_images, resolve_images};
 use super::to_html::parse_markdown;
 
 /// A structured representation of the parsed blogs, grouped by language.
@@ -30,12 +34,16 @@ struct Blog {
 }
 
 /// Dumps all blogs from storage into a JSON file. Blogs are stored as Markdown files.
+/// Other files in a language's folder, such as images, and any subfolders are not blogs so are skipped.
+/// Local images referenced by the blogs are copied into the public folder with content-hashed filenames.
+/// DOT diagrams are pre-rendered to SVG if Graphviz is installed.
 ///
 /// # Arguments
+/// * `public_folder` - The folder where referenced images are copied.
 /// * `pretty` - If true, the JSON output will be pretty-printed.
 ///
 /// # Errors
-/// If there was an error reading blog files or writing blogs to JSON.
+/// If there was an error reading blog files or writing blogs to JSON, a referenced image is missing or a diagram could not be rendered.
 ///
 /// # Examples
 /// ```
@@ -78,7 +86,8 @@ struct Blog {
 /// let blogs = setup_testing_blogs(contents);
 /// let mut dump_file = create_json_dump_file();
 ///
-/// dump_blogs(&blogs.path(), &dump_file.path(), false).expect("failed to dump blogs");
+/// let public = tempdir().expect("could not create temporary public directory");
+/// dump_blogs(&blogs.path(), &dump_file.path(), &public.path(), false).expect("failed to dump blogs");
 /// let mut dump_contents = String::new();
 /// &mut dump_file
 ///     .read_to_string(&mut dump_contents)
@@ -91,6 +100,7 @@ struct Blog {
 pub fn dump_blogs(
     markdown_blog_folder: &Path,
     json_dump_path: &Path,
+    public_folder: &Path,
     pretty: bool,
 ) -> Result<(), std::io::Error> {
     info!("commencing dump of markdown blogs to json");
@@ -113,7 +123,17 @@ pub fn dump_blogs(
 
         for entry in read_dir(&lang)? {
             let blog = entry?.path();
-            let html = parse_blog(&blog)?;
+            if !is_blog(&blog) {
+                info!("skipping {} as it is not a markdown file", blog.display());
+                continue;
+            }
+
+            let mut html = parse_blog(&blog)?;
+            for asset in resolve_images(&mut html, &lang)? {
+                info!("{} references image {}", blog.display(), asset.display());
+            }
+            publish_images(&mut html, markdown_blog_folder, public_folder)?;
+            render_diagrams(&mut html)?;
             let title = prepare_title(&blog)?;
             language.blogs.push(Blog { title, html });
         }
@@ -128,6 +148,14 @@ pub fn dump_blogs(
     Ok(())
 }
 
+/// Whether the path is a blog, i.e. a file with the `.md` extension.
+///
+/// # Arguments
+/// * `path` - The path to an entry in a language's folder.
+fn is_blog(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|extension| extension == "md")
+}
+
 /// Parses a blog from Markdown into HTML representation.
 ///
 /// # Arguments
@@ -236,14 +264,19 @@ fn prepare_title(blog: &Path) -> Result<String, std::io::Error> {
 
 #[cfg(test)]
 mod tests {
+    use std::fs::{create_dir, write};
     use std::path::PathBuf;
 
+    use tempfile::tempdir;
+
     use super::*;
+    use crate::parse::inline::parse_inline;
 
     #[test]
     fn test_doctest() {}
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_basename_good() {
         let mut path = PathBuf::new();
         path.push("root");
@@ -256,6 +289,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_lang_name_good() {
         let mut path = PathBuf::new();
         path.push("root");
@@ -273,7 +307,7 @@ mod tests {
             blogs: vec![Blog {
                 title: "My Blog Post".to_string(),
                 html: vec![HTMLElement::Paragraph {
-                    lines: vec!["This is the content of my blog post.".to_string()],
+                    lines: vec![parse_inline("This is the content of my blog post.")],
                 }],
             }],
         }];
@@ -297,4 +331,34 @@ mod tests {
         let title = prepare_title(&blog).expect("Failed to prepare title");
         assert_eq!(title, "my blog post");
     }
+
+    #[test]
+    fn test_dump_skips_files_other_than_blogs() {
+        let root = tempdir().expect("could not create temporary directory");
+        let blogs = root.path().join("blogs");
+        let lang = blogs.join("rust");
+        create_dir(&blogs).expect("could not create blogs folder");
+        create_dir(&lang).expect("could not create language folder");
+        create_dir(lang.join("drafts")).expect("could not create subfolder");
+        write(lang.join("my-post.md"), "# Overview").expect("could not write blog");
+        write(
+            lang.join("screenshot.png"),
+            [0x89, b'P', b'N', b'G', 0xff, 0xfe],
+        )
+        .expect("could not write image");
+        write(lang.join("diagram.svg"), "<svg></svg>").expect("could not write image");
+
+        let dump = root.path().join("dump.json");
+        let public = root.path().join("public");
+        dump_blogs(&blogs, &dump, &public, false).expect("failed to dump blogs");
+
+        let dumped: serde_json::Value =
+            serde_json::from_str(&read_to_string(&dump).expect("could not read dumped file"))
+                .expect("dumped file is not JSON");
+        let posts = dumped[0]["blogs"]
+            .as_array()
+            .expect("language has no blogs");
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0]["title"], "my post");
+    }
 }
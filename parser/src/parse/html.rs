@@ -0,0 +1,477 @@
+//! This module recognises raw HTML written in Markdown and sanitises it.
+//! Only tags and attributes on an allow-list are kept, so that blog content cannot inject scripts into the website.
+
+use std::collections::{HashMap, HashSet};
+
+/// Tags whose contents are removed along with the tag when they are not allowed.
+const DROP_CONTENT: [&str; 8] = [
+    "script", "style", "iframe", "object", "embed", "noscript", "template", "textarea",
+];
+
+/// Attributes holding a URL, which must not use a scheme such as `javascript:`.
+const URL_ATTRIBUTES: [&str; 4] = ["href", "src", "cite", "action"];
+
+/// URL schemes that are safe to link to.
+const SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// A HTML tag, such as `<a href="url">` or `</a>`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Tag {
+    /// Lowercase name of the tag
+    pub(crate) name: String,
+    /// Attribute names and their values, which are `None` for attributes without a value
+    pub(crate) attributes: Vec<(String, Option<String>)>,
+    /// Whether this is a closing tag like `</a>`
+    pub(crate) closing: bool,
+    /// Whether this is a self-closing tag like `<br/>`
+    pub(crate) self_closing: bool,
+}
+
+/// Try to parse a HTML tag starting at the given position.
+/// Return the tag and the position directly after it.
+pub(crate) fn parse_tag(chars: &[char], start: usize) -> Option<(Tag, usize)> {
+    if chars.get(start) != Some(&'<') {
+        return None;
+    }
+
+    let closing = chars.get(start + 1) == Some(&'/');
+    let name_start = start + 1 + closing as usize;
+    if !chars.get(name_start)?.is_ascii_alphabetic() {
+        return None;
+    }
+    let name_end = name_start
+        + chars[name_start..]
+            .iter()
+            .position(|c| !c.is_ascii_alphanumeric() && *c != '-')
+            .unwrap_or(chars.len() - name_start);
+
+    let mut tag = Tag {
+        name: chars[name_start..name_end]
+            .iter()
+            .collect::<String>()
+            .to_lowercase(),
+        attributes: Vec::new(),
+        closing,
+        self_closing: false,
+    };
+
+    let mut i = name_end;
+    loop {
+        let spaces = skip_whitespace(chars, i);
+        i += spaces;
+        match chars.get(i)? {
+            '>' => return Some((tag, i + 1)),
+            '/' if !closing && chars.get(i + 1) == Some(&'>') => {
+                tag.self_closing = true;
+                return Some((tag, i + 2));
+            }
+            // closing tags cannot have attributes, which must be separated by whitespace
+            _ if closing || spaces == 0 => return None,
+            _ => {
+                let (attribute, end) = parse_attribute(chars, i)?;
+                tag.attributes.push(attribute);
+                i = end;
+            }
+        }
+    }
+}
+
+/// Try to parse a HTML comment like `<!-- comment -->` starting at the given position.
+/// Return the position directly after it.
+pub(crate) fn parse_comment(chars: &[char], start: usize) -> Option<usize> {
+    let open = ['<', '!', '-', '-'];
+    if !chars[start..].starts_with(&open) {
+        return None;
+    }
+    let body = start + open.len();
+    chars[body..]
+        .windows(3)
+        .position(|window| window == ['-', '-', '>'])
+        .map(|end| body + end + 3)
+}
+
+/// Return the number of whitespace characters starting at the given position.
+fn skip_whitespace(chars: &[char], start: usize) -> usize {
+    chars[start.min(chars.len())..]
+        .iter()
+        .take_while(|c| c.is_whitespace())
+        .count()
+}
+
+/// Parse an attribute like `name`, `name=value`, `name='value'` or `name="value"`.
+/// Return the attribute and the position directly after it.
+fn parse_attribute(chars: &[char], start: usize) -> Option<((String, Option<String>), usize)> {
+    let first = chars.get(start)?;
+    if !first.is_ascii_alphabetic() && *first != '_' && *first != ':' {
+        return None;
+    }
+    let name_end = start
+        + chars[start..]
+            .iter()
+            .position(|c| !c.is_ascii_alphanumeric() && !matches!(c, '_' | '.' | ':' | '-'))
+            .unwrap_or(chars.len() - start);
+    let name = chars[start..name_end]
+        .iter()
+        .collect::<String>()
+        .to_lowercase();
+
+    let equals = name_end + skip_whitespace(chars, name_end);
+    if chars.get(equals) != Some(&'=') {
+        return Some(((name, None), name_end));
+    }
+
+    let value_start = equals + 1 + skip_whitespace(chars, equals + 1);
+    let (value, end) = match chars.get(value_start)? {
+        quote @ ('"' | '\'') => {
+            let length = chars[value_start + 1..].iter().position(|c| c == quote)?;
+            let value_end = value_start + 1 + length;
+            (&chars[value_start + 1..value_end], value_end + 1)
+        }
+        _ => {
+            let length = chars[value_start..]
+                .iter()
+                .position(|c| c.is_whitespace() || "\"'=<>`".contains(*c))
+                .unwrap_or(chars.len() - value_start);
+            if length == 0 {
+                return None;
+            }
+            (
+                &chars[value_start..value_start + length],
+                value_start + length,
+            )
+        }
+    };
+    Some(((name, Some(value.iter().collect())), end))
+}
+
+/// Removes any HTML attributes that are not on an allow-list, and escapes tags that are not allowed.
+/// Escaped tags are shown as plain text, so text like `Vec<int>` is not lost.
+/// Tags like `<script>` are instead removed along with their contents.
+/// Event handler attributes such as `onclick` and URLs with unsafe schemes such as `javascript:` are always removed.
+///
+/// # Examples
+/// ```
+/// # use parser::parse::html::HtmlSanitizer;
+///
+/// let sanitizer = HtmlSanitizer::new().allow("kbd", &[]).allow("a", &["href"]);
+/// assert_eq!(
+///     sanitizer.sanitize(r#"<kbd onclick="steal()">Ctrl</kbd><a href="javascript:steal()">x</a><b>y</b>"#),
+///     "<kbd>Ctrl</kbd><a>x</a>&lt;b&gt;y&lt;/b&gt;"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct HtmlSanitizer {
+    /// Allowed tag names mapped to their allowed attribute names
+    allowed: HashMap<String, HashSet<String>>,
+}
+
+impl Default for HtmlSanitizer {
+    /// Allow tags for formatting text, lists, tables and disclosure widgets.
+    fn default() -> Self {
+        let text = [
+            "b", "br", "code", "del", "em", "hr", "i", "ins", "kbd", "mark", "p", "pre", "s",
+            "samp", "small", "span", "strong", "sub", "summary", "sup", "u", "var",
+        ];
+        let containers = [
+            "blockquote",
+            "dd",
+            "div",
+            "dl",
+            "dt",
+            "h1",
+            "h2",
+            "h3",
+            "h4",
+            "h5",
+            "h6",
+            "li",
+            "table",
+            "tbody",
+            "tfoot",
+            "thead",
+            "tr",
+            "ul",
+        ];
+        let sanitizer = text
+            .iter()
+            .chain(containers.iter())
+            .fold(HtmlSanitizer::new(), |sanitizer, tag| {
+                sanitizer.allow(tag, &[])
+            });
+
+        sanitizer
+            .allow("a", &["href", "title"])
+            .allow("abbr", &["title"])
+            .allow("details", &["open"])
+            .allow("img", &["src", "alt", "title", "width", "height"])
+            .allow("ol", &["start"])
+            .allow("td", &["align", "colspan", "rowspan"])
+            .allow("th", &["align", "colspan", "rowspan"])
+    }
+}
+
+impl HtmlSanitizer {
+    /// Create a sanitizer that does not allow any tags.
+    pub fn new() -> Self {
+        HtmlSanitizer {
+            allowed: HashMap::new(),
+        }
+    }
+
+    /// Allow a tag with the given attributes, adding to any attributes already allowed for the tag.
+    ///
+    /// # Arguments
+    /// * `tag` - Name of the tag, ignoring case.
+    /// * `attributes` - Names of the allowed attributes, ignoring case.
+    pub fn allow(mut self, tag: &str, attributes: &[&str]) -> Self {
+        self.allowed
+            .entry(tag.to_lowercase())
+            .or_default()
+            .extend(attributes.iter().map(|attribute| attribute.to_lowercase()));
+        self
+    }
+
+    /// Return the HTML with any attributes that are not allowed removed and any tags that are not allowed escaped.
+    /// Comments are removed, and any `'<'` or `'>'` that is not part of a tag is escaped.
+    ///
+    /// # Arguments
+    /// * `html` - The raw HTML.
+    pub fn sanitize(&self, html: &str) -> String {
+        let chars = html.chars().collect::<Vec<char>>();
+        let mut sanitized = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if let Some(end) = parse_comment(&chars, i) {
+                i = end;
+            } else if let Some((tag, end)) = parse_tag(&chars, i) {
+                match self.allowed.get(&tag.name) {
+                    Some(attributes) => sanitized.push_str(&self.write_tag(&tag, attributes)),
+                    None if DROP_CONTENT.contains(&tag.name.as_str()) => {
+                        if !tag.closing {
+                            i = find_closing_tag(&chars, end, &tag.name);
+                            continue;
+                        }
+                    }
+                    None => sanitized.push_str(&escape(&chars[i..end].iter().collect::<String>())),
+                }
+                i = end;
+            } else {
+                match chars[i] {
+                    '<' => sanitized.push_str("&lt;"),
+                    '>' => sanitized.push_str("&gt;"),
+                    c => sanitized.push(c),
+                }
+                i += 1;
+            }
+        }
+        sanitized
+    }
+
+    /// Sanitise a single tag or comment, like `sanitize`.
+    /// Return `None` if it is a tag that is not allowed, so that it can be kept as plain text instead.
+    ///
+    /// # Arguments
+    /// * `html` - The raw HTML of one tag or comment.
+    pub(crate) fn sanitize_tag(&self, html: &str) -> Option<String> {
+        let chars = html.chars().collect::<Vec<char>>();
+        match parse_tag(&chars, 0) {
+            Some((tag, _)) if !self.allowed.contains_key(&tag.name) => None,
+            _ => Some(self.sanitize(html)),
+        }
+    }
+
+    /// Write an allowed tag with only its allowed attributes, which are quoted and escaped.
+    fn write_tag(&self, tag: &Tag, allowed: &HashSet<String>) -> String {
+        if tag.closing {
+            return format!("</{}>", tag.name);
+        }
+
+        let mut written = format!("<{}", tag.name);
+        for (name, value) in &tag.attributes {
+            // browsers decode character references in values, so they are checked and escaped once decoded
+            let value = value.as_deref().map(decode_entities);
+            let is_safe = allowed.contains(name)
+                && !name.starts_with("on")
+                && (!URL_ATTRIBUTES.contains(&name.as_str())
+                    || value.as_deref().is_some_and(is_safe_url));
+            if !is_safe {
+                continue;
+            }
+            match value {
+                Some(value) => written.push_str(&format!(" {}=\"{}\"", name, escape(&value))),
+                None => written.push_str(&format!(" {}", name)),
+            }
+        }
+        if tag.self_closing {
+            written.push_str(" /");
+        }
+        written.push('>');
+        written
+    }
+}
+
+/// Return the position after the closing tag with the given name, or the end of the text if it is never closed.
+fn find_closing_tag(chars: &[char], start: usize, name: &str) -> usize {
+    (start..chars.len())
+        .find_map(|i| {
+            parse_tag(chars, i)
+                .filter(|(tag, _)| tag.closing && tag.name == name)
+                .map(|(_, end)| end)
+        })
+        .unwrap_or(chars.len())
+}
+
+/// Whether a URL is relative or uses a safe scheme.
+/// Whitespace and control characters are ignored, since browsers also ignore them.
+fn is_safe_url(url: &str) -> bool {
+    let url = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_lowercase();
+    let scheme_end = url.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(end) if url[end..].starts_with(':') => SAFE_SCHEMES.contains(&&url[..end]),
+        _ => true,
+    }
+}
+
+/// Decode the character references in an attribute value, like `&amp;`, `&#58;` or `&#x3a;`.
+/// Unknown named references are kept as they are.
+fn decode_entities(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest
+            .find(';')
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match reference {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Return the character named by a character reference, without its leading `'&'` and trailing `';'`.
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Escape text for use as plain text or inside a quoted attribute value.
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        let text = chars(r#"<A href="x y" hidden data-n=1 title='a'/>"#);
+        assert_eq!(
+            parse_tag(&text, 0),
+            Some((
+                Tag {
+                    name: "a".to_string(),
+                    attributes: vec![
+                        ("href".to_string(), Some("x y".to_string())),
+                        ("hidden".to_string(), None),
+                        ("data-n".to_string(), Some("1".to_string())),
+                        ("title".to_string(), Some("a".to_string())),
+                    ],
+                    closing: false,
+                    self_closing: true,
+                },
+                text.len()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_tags() {
+        for text in ["< a>", "<a", "<a href=>", "</a b>", "<a\"b>", "<1>"] {
+            assert_eq!(parse_tag(&chars(text), 0), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_comment() {
+        let text = chars("<!-- a > b -->c");
+        assert_eq!(parse_comment(&text, 0), Some(14));
+        assert_eq!(parse_comment(&chars("<!-- open"), 0), None);
+    }
+
+    /// Test that only allowed tags and attributes are kept by the default sanitizer.
+    #[test]
+    fn test_sanitize_default() {
+        let sanitizer = HtmlSanitizer::default();
+        assert_eq!(
+            sanitizer.sanitize(
+                r#"<details open><summary>More</summary><img src=a.png onerror="alert(1)"></details>"#
+            ),
+            r#"<details open><summary>More</summary><img src="a.png"></details>"#
+        );
+        assert_eq!(
+            sanitizer.sanitize("<p>a<script>alert('<p>')</script>b<!-- note --></p><center>"),
+            "<p>ab</p>&lt;center&gt;"
+        );
+        assert_eq!(
+            sanitizer.sanitize(r#"<a href=" JavaScript:alert(1)" title="a&quot;">x</a> 1 < 2"#),
+            r#"<a title="a&quot;">x</a> 1 &lt; 2"#
+        );
+        assert_eq!(
+            sanitizer
+                .sanitize(r#"<a href="?x=1&amp;y=2" title="a&amp;b &copy; &#65;&#x42;">x</a>"#),
+            r#"<a href="?x=1&amp;y=2" title="a&amp;b &amp;copy; AB">x</a>"#
+        );
+        assert_eq!(
+            sanitizer.sanitize(r#"<a href="javascript&#58;alert(1)">x</a>"#),
+            "<a>x</a>"
+        );
+    }
+
+    #[test]
+    fn test_safe_urls() {
+        assert!(is_safe_url("https://example.com"));
+        assert!(is_safe_url("images/a.png"));
+        assert!(is_safe_url("/a:b"));
+        assert!(is_safe_url("#top"));
+        assert!(!is_safe_url("java\nscript:alert(1)"));
+        assert!(!is_safe_url("data:text/html,x"));
+    }
+}
@@ -1,20 +1,239 @@
 //! A dataclass to store parsed Markdown, ready for conversion into a structured JSON format.
 
+use crate::parse::inline::{Image, Inline};
+
 /// A structured representation of HTML elements parsed from Markdown.
 /// This enum captures various HTML elements that can be generated from Markdown content.
+/// Text that can contain inline Markdown is stored as a list of inline elements.
 #[derive(Debug, PartialEq, serde::Serialize)]
 #[serde(tag = "type")]
 pub enum HTMLElement {
     /// Header blocks
     Header { level: usize, content: String },
-    /// Code snippets
-    Code { language: String, code: Vec<String> },
-    /// Ordered lists
-    OrderedList { list: Vec<String> },
-    /// Unordered lists
-    UnorderedList { list: Vec<String> },
-    /// HTML tables - the length of headers and rows must match
-    Table { headers: Vec<String>, rows: Vec<Vec<String>> },
+    /// Code snippets - highlighted line ranges are numbered from 1
+    Code {
+        language: String,
+        title: Option<String>,
+        highlight: Vec<LineRange>,
+        line_numbers: bool,
+        code: Vec<String>,
+    },
+    /// Ordered lists - tight lists have no blank lines between or inside their items
+    OrderedList {
+        start: usize,
+        list: Vec<ListItem>,
+        tight: bool,
+    },
+    /// Unordered lists - tight lists have no blank lines between or inside their items
+    UnorderedList { list: Vec<ListItem>, tight: bool },
+    /// HTML tables - the length of headers, alignment and rows must match
+    Table {
+        headers: Vec<Vec<Inline>>,
+        alignment: Vec<Option<Alignment>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    /// Horizontal rules separating sections
+    Rule,
+    /// Images on a line of their own
+    Image(Image),
     /// Paragraph text
-    Paragraph { lines: Vec<String> },
+    Paragraph { lines: Vec<Vec<Inline>> },
+    /// Quoted text which can contain any other element
+    Blockquote { children: Vec<HTMLElement> },
+    /// Blockquotes which are highlighted as a particular kind of message
+    Callout {
+        kind: CalloutKind,
+        children: Vec<HTMLElement>,
+    },
+    /// Footnotes at the end of the document, ordered by their first reference
+    Footnotes { notes: Vec<Footnote> },
+    /// Raw HTML which has been sanitised
+    HtmlBlock { html: String },
+    /// Collapsible sections which can contain any other element
+    Details {
+        summary: Vec<Inline>,
+        children: Vec<HTMLElement>,
+    },
+    /// Terms followed by their definitions
+    DefinitionList { items: Vec<DefinitionItem> },
+    /// TeX math which is kept verbatim - math blocks are always display math
+    Math { display: bool, tex: String },
+    /// Diagrams drawn from their source code - DOT diagrams can be pre-rendered to SVG
+    Diagram {
+        kind: DiagramKind,
+        source: String,
+        svg: Option<String>,
+    },
+}
+
+/// An item of an ordered or unordered list.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ListItem {
+    /// Whether a task list checkbox is ticked, or null if the item has no checkbox
+    pub checked: Option<bool>,
+    /// Lines of the paragraph following the list marker, which can be empty
+    pub lines: Vec<Vec<Inline>>,
+    /// Elements after the leading paragraph, such as child lists, code blocks and further paragraphs
+    pub children: Vec<HTMLElement>,
+}
+
+/// A term of a definition list along with its definitions.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct DefinitionItem {
+    /// Term being defined
+    pub term: Vec<Inline>,
+    /// Elements of each definition, in the order they appear
+    pub definitions: Vec<Vec<HTMLElement>>,
+}
+
+/// A footnote definition, which is numbered by the order of its first reference.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Footnote {
+    /// Number shown for the footnote, starting from 1
+    pub number: usize,
+    /// Label used by references in the Markdown, e.g. `note` for `[^note]`
+    pub label: String,
+    /// Number of references to the footnote, which each need a back-reference
+    pub references: usize,
+    /// Elements of the footnote's text
+    pub children: Vec<HTMLElement>,
+}
+
+/// An inclusive range of lines in a code block.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct LineRange {
+    /// First line of the range
+    pub start: usize,
+    /// Last line of the range
+    pub end: usize,
+}
+
+/// The alignment of a table column.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub enum Alignment {
+    /// Column separator cell formatted as `:---`
+    Left,
+    /// Column separator cell formatted as `:---:`
+    Center,
+    /// Column separator cell formatted as `---:`
+    Right,
+}
+
+/// The kinds of callouts supported by GitHub flavoured Markdown.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub enum CalloutKind {
+    /// Useful information for skimming readers
+    Note,
+    /// Advice for doing things more easily
+    Tip,
+    /// Key information needed to achieve a goal
+    Important,
+    /// Urgent information needing immediate attention to avoid problems
+    Warning,
+    /// Advice about the negative consequences of an action
+    Caution,
+}
+
+/// The languages that diagrams can be written in.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub enum DiagramKind {
+    /// Diagrams drawn by Mermaid, written in a code block tagged `mermaid`
+    Mermaid,
+    /// Graphviz graphs, written in a code block tagged `dot`
+    Dot,
+}
+
+/// Call the function on every element, followed by the elements nested inside it.
+/// Nested elements are found inside list items, blockquotes, callouts, footnotes, collapsible sections and definitions.
+pub(crate) fn for_each_element(elements: &mut [HTMLElement], f: &mut dyn FnMut(&mut HTMLElement)) {
+    for element in elements {
+        f(element);
+        match element {
+            HTMLElement::OrderedList { list, .. } | HTMLElement::UnorderedList { list, .. } => {
+                for item in list {
+                    for_each_element(&mut item.children, f);
+                }
+            }
+            HTMLElement::Blockquote { children }
+            | HTMLElement::Callout { children, .. }
+            | HTMLElement::Details { children, .. } => for_each_element(children, f),
+            HTMLElement::Footnotes { notes } => {
+                for note in notes {
+                    for_each_element(&mut note.children, f);
+                }
+            }
+            HTMLElement::DefinitionList { items } => {
+                for definition in items.iter_mut().flat_map(|item| &mut item.definitions) {
+                    for_each_element(definition, f);
+                }
+            }
+            HTMLElement::Header { .. }
+            | HTMLElement::Code { .. }
+            | HTMLElement::Table { .. }
+            | HTMLElement::Paragraph { .. }
+            | HTMLElement::Rule
+            | HTMLElement::Image(_)
+            | HTMLElement::HtmlBlock { .. }
+            | HTMLElement::Math { .. }
+            | HTMLElement::Diagram { .. } => {}
+        }
+    }
+}
+
+/// Call the function on every line of inline elements inside the elements, in the order they appear.
+/// Each table cell is a separate line.
+pub(crate) fn for_each_line(elements: &mut [HTMLElement], f: &mut dyn FnMut(&mut Vec<Inline>)) {
+    for_each_element(elements, &mut |element| match element {
+        HTMLElement::OrderedList { list, .. } | HTMLElement::UnorderedList { list, .. } => {
+            for item in list {
+                item.lines.iter_mut().for_each(&mut *f);
+            }
+        }
+        HTMLElement::Table { headers, rows, .. } => {
+            headers.iter_mut().for_each(&mut *f);
+            rows.iter_mut().flatten().for_each(&mut *f);
+        }
+        HTMLElement::Paragraph { lines } => lines.iter_mut().for_each(&mut *f),
+        HTMLElement::Details { summary, .. } => f(summary),
+        HTMLElement::DefinitionList { items } => {
+            items.iter_mut().for_each(|item| f(&mut item.term))
+        }
+        _ => {}
+    });
+}
+
+/// Call the function on every inline element inside the elements, in the order they appear.
+/// Inline elements nested inside formatting or links are also visited.
+pub(crate) fn for_each_inline(elements: &mut [HTMLElement], f: &mut dyn FnMut(&mut Inline)) {
+    for_each_line(elements, &mut |line| {
+        for_each_nested_inline(line.iter_mut(), f)
+    });
+}
+
+/// Call the function on every inline element, followed by the inline elements nested inside it.
+fn for_each_nested_inline<'a>(
+    inlines: impl Iterator<Item = &'a mut Inline>,
+    f: &mut dyn FnMut(&mut Inline),
+) {
+    for inline in inlines {
+        f(inline);
+        match inline {
+            Inline::Strong { children }
+            | Inline::Emphasis { children }
+            | Inline::Strikethrough { children }
+            | Inline::Mark { children }
+            | Inline::Link {
+                label: children, ..
+            }
+            | Inline::LinkReference {
+                label: children, ..
+            } => for_each_nested_inline(children.iter_mut(), f),
+            Inline::Text { .. }
+            | Inline::Code { .. }
+            | Inline::Math { .. }
+            | Inline::Image(_)
+            | Inline::FootnoteReference { .. }
+            | Inline::Html { .. } => {}
+        }
+    }
 }
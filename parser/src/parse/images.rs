@@ -0,0 +1,419 @@
+//! Resolves the images referenced by a parsed blog and publishes them to the website.
+//! Relative image paths are written relative to the blog's language folder, e.g. `blog/lang/<lang>/`.
+//! Published images are named by a hash of their contents so that browsers never show a stale copy.
+
+use log::info;
+use std::fs::{create_dir_all, read, write};
+use std::path::{Component, Path, PathBuf};
+
+use super::html_element::{for_each_element, for_each_inline, HTMLElement};
+use super::inline::{Image, Inline};
+
+/// Resolves the relative paths of every image in a parsed blog against the blog's language folder.
+/// Each resolved image source is rewritten to be relative to the folder of all languages, e.g. `cpp/diagram.png`.
+/// Images with URLs or absolute paths are left unchanged.
+/// Return the paths of the local images referenced by the blog.
+///
+/// # Arguments
+/// * `html` - The parsed elements of the blog.
+/// * `lang` - The path to the language directory holding the blog.
+///
+/// # Errors
+/// If an image path leaves the folder of all languages.
+pub fn resolve_images(
+    html: &mut [HTMLElement],
+    lang: &Path,
+) -> Result<Vec<PathBuf>, std::io::Error> {
+    let root = lang.parent().unwrap_or(Path::new(""));
+    let lang_name = lang
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut assets = Vec::new();
+    for_each_image(html, &mut |image| {
+        if !is_local(&image.src) {
+            return Ok(());
+        }
+
+        let resolved = normalise(&lang_name, &image.src)?;
+        info!("resolved image {} to {}", image.src, resolved);
+        assets.push(root.join(&resolved));
+        image.src = resolved;
+        Ok(())
+    })?;
+    Ok(assets)
+}
+
+/// Subfolder of the website's public folder holding published images.
+const PUBLIC_IMAGES: &str = "images";
+
+/// Copies the local images of a parsed blog into the website's public folder.
+/// The images must have already been resolved by `resolve_images`.
+/// Each copy is named with a hash of its contents, and the image source is rewritten to the published path, e.g. `/images/diagram.0123456789abcdef.png`.
+/// The width and height of PNG, JPEG and SVG images are also recorded.
+///
+/// # Arguments
+/// * `html` - The parsed elements of the blog.
+/// * `markdown_blog_folder` - The folder of all languages that image sources are relative to.
+/// * `public_folder` - The website's public folder.
+///
+/// # Errors
+/// If a referenced image does not exist, or there was an error copying it.
+pub fn publish_images(
+    html: &mut [HTMLElement],
+    markdown_blog_folder: &Path,
+    public_folder: &Path,
+) -> Result<(), std::io::Error> {
+    let images_folder = public_folder.join(PUBLIC_IMAGES);
+    for_each_image(html, &mut |image| {
+        if !is_local(&image.src) {
+            return Ok(());
+        }
+
+        let path = markdown_blog_folder.join(&image.src);
+        let contents = read(&path).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("could not read image {}: {}", path.display(), e),
+            )
+        })?;
+
+        let name = hashed_name(Path::new(&image.src), &contents);
+        create_dir_all(&images_folder)?;
+        write(images_folder.join(&name), &contents)?;
+        info!("published image {} as {}", path.display(), name);
+
+        if let Some((width, height)) = image_dimensions(&contents) {
+            image.width = Some(width);
+            image.height = Some(height);
+        }
+        image.src = format!("/{}/{}", PUBLIC_IMAGES, name);
+        Ok(())
+    })
+}
+
+/// Insert a hash of the contents between the file's stem and extension.
+fn hashed_name(path: &Path, contents: &[u8]) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let hash = fnv_hash(contents);
+    match path.extension() {
+        Some(extension) => format!("{}.{:016x}.{}", stem, hash, extension.to_string_lossy()),
+        None => format!("{}.{:016x}", stem, hash),
+    }
+}
+
+/// Hash bytes with the 64 bit FNV-1a hash, which is stable between builds.
+fn fnv_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Read the width and height of an image from the header of a PNG, JPEG or SVG file.
+/// Return `None` for other formats, or if the size cannot be found.
+fn image_dimensions(contents: &[u8]) -> Option<(u32, u32)> {
+    if contents.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_dimensions(contents)
+    } else if contents.starts_with(&[0xff, 0xd8]) {
+        jpeg_dimensions(contents)
+    } else {
+        svg_dimensions(std::str::from_utf8(contents).ok()?)
+    }
+}
+
+/// The size of a PNG is stored at the start of the first chunk, which is always the header chunk.
+fn png_dimensions(contents: &[u8]) -> Option<(u32, u32)> {
+    let width = contents.get(16..20)?;
+    let height = contents.get(20..24)?;
+    Some((
+        u32::from_be_bytes(width.try_into().ok()?),
+        u32::from_be_bytes(height.try_into().ok()?),
+    ))
+}
+
+/// The size of a JPEG is stored in its start of frame segment, which is found by skipping the segments before it.
+fn jpeg_dimensions(contents: &[u8]) -> Option<(u32, u32)> {
+    let read_u16 = |i: usize| -> Option<u32> {
+        let bytes = contents.get(i..i + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]) as u32)
+    };
+
+    let mut i = 2;
+    while i + 1 < contents.len() {
+        if contents[i] != 0xff {
+            return None;
+        }
+        let marker = contents[i + 1];
+        match marker {
+            // padding before a marker
+            0xff => i += 1,
+            // markers without a segment
+            0x01 | 0xd0..=0xd9 => i += 2,
+            // start of frame markers, excluding the huffman, arithmetic coding and jpeg extension markers
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                return Some((read_u16(i + 7)?, read_u16(i + 5)?));
+            }
+            _ => i += 2 + read_u16(i + 2)? as usize,
+        }
+    }
+    None
+}
+
+/// The size of an SVG is taken from the `width` and `height` attributes of its root tag, otherwise its `viewBox`.
+fn svg_dimensions(contents: &str) -> Option<(u32, u32)> {
+    let start = contents.find("<svg")?;
+    let tag = &contents[start..start + contents[start..].find('>')?];
+
+    let size = svg_attribute(tag, "width")
+        .and_then(parse_length)
+        .zip(svg_attribute(tag, "height").and_then(parse_length));
+    size.or_else(|| {
+        let view_box = svg_attribute(tag, "viewBox")?
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|part| !part.is_empty())
+            .map(parse_length)
+            .collect::<Option<Vec<u32>>>()?;
+        match view_box.as_slice() {
+            [_, _, width, height] => Some((*width, *height)),
+            _ => None,
+        }
+    })
+}
+
+/// Find the value of an attribute inside a tag, which must be quoted.
+fn svg_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut search = 0;
+    while let Some(found) = tag[search..].find(name) {
+        let start = search + found;
+        let rest = &tag[start + name.len()..];
+        // the name must be a whole attribute, e.g. not `stroke-width`
+        let separated = tag[..start].ends_with(char::is_whitespace);
+        if let Some(quoted) = rest.trim_start().strip_prefix('=').map(str::trim_start) {
+            let quote = quoted.chars().next().filter(|&c| c == '"' || c == '\'');
+            if let (true, Some(quote)) = (separated, quote) {
+                let value = &quoted[1..];
+                return value.find(quote).map(|end| &value[..end]);
+            }
+        }
+        search = start + name.len();
+    }
+    None
+}
+
+/// Parse a length in pixels, such as `24`, `24.5` or `24px`, rounding to the nearest pixel.
+/// Other units such as percentages cannot be converted to pixels.
+fn parse_length(length: &str) -> Option<u32> {
+    let number = length.trim().trim_end_matches("px");
+    let value = number.parse::<f64>().ok()?;
+    (value >= 0.0).then_some(value.round() as u32)
+}
+
+/// Whether an image source is a relative path to a local file rather than a URL or absolute path.
+fn is_local(src: &str) -> bool {
+    !src.is_empty() && !src.contains("://") && !src.starts_with('/') && !src.starts_with("data:")
+}
+
+/// Join a relative path onto the language folder, removing any `.` and `..` components.
+/// The result always uses `'/'` as a separator so that it can be used in the website.
+///
+/// # Errors
+/// If the path leaves the folder of all languages.
+fn normalise(lang_name: &str, src: &str) -> Result<String, std::io::Error> {
+    let mut parts = vec![lang_name.to_string()];
+    for component in Path::new(src).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir if parts.pop().is_some() => {}
+            Component::CurDir => {}
+            _ => {
+                return Err(std::io::Error::other(format!(
+                    "image path {} leaves the blog folder",
+                    src
+                )))
+            }
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+/// Call the function on every image inside the elements, including images inside text.
+/// Stop at the first error.
+fn for_each_image(
+    elements: &mut [HTMLElement],
+    f: &mut dyn FnMut(&mut Image) -> Result<(), std::io::Error>,
+) -> Result<(), std::io::Error> {
+    let mut result = Ok(());
+    for_each_element(elements, &mut |element| {
+        if let (HTMLElement::Image(image), Ok(())) = (element, &result) {
+            result = f(image);
+        }
+    });
+    for_each_inline(elements, &mut |inline| {
+        if let (Inline::Image(image), Ok(())) = (inline, &result) {
+            result = f(image);
+        }
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::parse::to_html::parse_markdown;
+
+    /// Test that relative images are resolved inside blocks and text, and other images are unchanged.
+    #[test]
+    fn test_resolve_images() {
+        let text = vec![
+            "![diagram](./images/diagram.png)".to_string(),
+            "".to_string(),
+            "- see **![icon](../py/icon.svg)**".to_string(),
+            "".to_string(),
+            "![remote](https://example.com/a.png)".to_string(),
+        ];
+        let mut html = parse_markdown(&text);
+        let lang = PathBuf::from("blog").join("lang").join("cpp");
+
+        let assets = resolve_images(&mut html, &lang).expect("failed to resolve images");
+        let root = PathBuf::from("blog").join("lang");
+        assert_eq!(
+            assets,
+            vec![
+                root.join("cpp/images/diagram.png"),
+                root.join("py/icon.svg")
+            ]
+        );
+        assert_eq!(
+            html[0],
+            HTMLElement::Image(Image {
+                src: "cpp/images/diagram.png".to_string(),
+                alt: "diagram".to_string(),
+                title: None,
+                width: None,
+                height: None,
+            })
+        );
+        assert_eq!(
+            html[2],
+            HTMLElement::Image(Image {
+                src: "https://example.com/a.png".to_string(),
+                alt: "remote".to_string(),
+                title: None,
+                width: None,
+                height: None,
+            })
+        );
+    }
+
+    /// Test that local images are copied with hashed names and sizes, while other images are unchanged.
+    #[test]
+    fn test_publish_images() {
+        let root = tempdir().expect("could not create temporary directory");
+        let blogs = root.path().join("lang");
+        let public = root.path().join("public");
+        create_dir_all(blogs.join("cpp")).expect("could not create language folder");
+        write(
+            blogs.join("cpp").join("icon.svg"),
+            r#"<svg width="24px" height="16">"#,
+        )
+        .expect("could not write image");
+
+        let text = vec![
+            "![icon](icon.svg)".to_string(),
+            "".to_string(),
+            "![remote](https://example.com/a.png)".to_string(),
+        ];
+        let mut html = parse_markdown(&text);
+        resolve_images(&mut html, &blogs.join("cpp")).expect("failed to resolve images");
+        publish_images(&mut html, &blogs, &public).expect("failed to publish images");
+
+        let name = hashed_name(Path::new("icon.svg"), br#"<svg width="24px" height="16">"#);
+        assert!(public.join("images").join(&name).exists());
+        assert_eq!(
+            html[0],
+            HTMLElement::Image(Image {
+                src: format!("/images/{}", name),
+                alt: "icon".to_string(),
+                title: None,
+                width: Some(24),
+                height: Some(16),
+            })
+        );
+        assert_eq!(
+            html[1],
+            HTMLElement::Image(Image {
+                src: "https://example.com/a.png".to_string(),
+                alt: "remote".to_string(),
+                title: None,
+                width: None,
+                height: None,
+            })
+        );
+    }
+
+    /// Test that publishing fails when an image does not exist.
+    #[test]
+    fn test_publish_missing_image() {
+        let root = tempdir().expect("could not create temporary directory");
+        let mut html = parse_markdown(&["![missing](cpp/missing.png)".to_string()]);
+        let error = publish_images(&mut html, root.path(), &root.path().join("public"))
+            .expect_err("missing image was published");
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_hashed_name() {
+        let name = hashed_name(Path::new("cpp/diagram.png"), b"contents");
+        assert!(name.starts_with("diagram."));
+        assert!(name.ends_with(".png"));
+        assert_ne!(name, hashed_name(Path::new("cpp/diagram.png"), b"changed"));
+    }
+
+    #[test]
+    fn test_png_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        assert_eq!(image_dimensions(&png), Some((640, 480)));
+    }
+
+    #[test]
+    fn test_jpeg_dimensions() {
+        let jpeg = [
+            0xff, 0xd8, // start of image
+            0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, // application segment
+            0xff, 0xc0, 0x00, 0x11, 0x08, 0x01, 0xe0, 0x02, 0x80, // start of frame
+        ];
+        assert_eq!(image_dimensions(&jpeg), Some((640, 480)));
+    }
+
+    #[test]
+    fn test_svg_dimensions() {
+        assert_eq!(
+            image_dimensions(
+                br#"<?xml version="1.0"?><svg stroke-width="2" viewBox="0 0 100 50">"#
+            ),
+            Some((100, 50))
+        );
+        assert_eq!(
+            image_dimensions(br#"<svg width="100%" height="100%">"#),
+            None
+        );
+    }
+
+    /// Test that images cannot be resolved outside of the blog folder.
+    #[test]
+    fn test_image_outside_blogs() {
+        let mut html = parse_markdown(&["![secret](../../secret.png)".to_string()]);
+        let lang = PathBuf::from("blog").join("lang").join("cpp");
+        assert!(resolve_images(&mut html, &lang).is_err());
+    }
+}
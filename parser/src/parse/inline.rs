@@ -0,0 +1,1375 @@
+//! This module provides a parser for inline Markdown features found inside block elements.
+//! Supported features are links, autolinks, images, footnote references, raw HTML, inline code and text formatting such as bold, italics, strikethrough and highlighting.
+
+use crate::parse::html::{parse_comment, parse_tag, HtmlSanitizer};
+
+use log::warn;
+use std::collections::HashMap;
+
+/// A structured representation of inline Markdown.
+/// Block elements store their text as a list of these nodes so every consumer shares the same inline semantics.
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum Inline {
+    /// Plain text with escaping backslashes removed
+    Text { text: String },
+    /// Inline code - the contents are not parsed any further
+    Code { code: String },
+    /// TeX math which is kept verbatim - display math is written with double dollar signs
+    Math { display: bool, tex: String },
+    /// Bold text
+    Strong { children: Vec<Inline> },
+    /// Italic text
+    Emphasis { children: Vec<Inline> },
+    /// Text with a line through it
+    Strikethrough { children: Vec<Inline> },
+    /// Highlighted text
+    Mark { children: Vec<Inline> },
+    /// Hyperlinks - the label can contain further inline elements
+    Link {
+        href: String,
+        title: Option<String>,
+        label: Vec<Inline>,
+    },
+    /// Raw HTML tags and comments, which are sanitised once the whole document has been parsed
+    Html { html: String },
+    /// References to a link definition like `[label][reference]`, which only exist while parsing a document
+    /// The suffix is the text after the label, e.g. `[reference]`, `[]` or nothing for a shortcut reference
+    #[serde(skip)]
+    LinkReference {
+        reference: String,
+        label: Vec<Inline>,
+        suffix: String,
+    },
+    /// Images shown within a line of text
+    Image(Image),
+    /// References to a footnote like `[^label]` - the number is set once the whole document has been parsed
+    /// The occurrence counts the references to the same footnote so far, starting from 1
+    FootnoteReference {
+        label: String,
+        number: usize,
+        occurrence: usize,
+    },
+}
+
+/// An image written like `![alt](src "title")`.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Image {
+    /// Path or URL of the image
+    pub src: String,
+    /// Plain text describing the image, with any inline formatting removed
+    pub alt: String,
+    /// Optional text shown when hovering over the image
+    pub title: Option<String>,
+    /// Width in pixels, known once a local image has been published
+    pub width: Option<u32>,
+    /// Height in pixels, known once a local image has been published
+    pub height: Option<u32>,
+}
+
+/// Returns the inline elements parsed from one line of Markdown text.
+/// A backslash can be used to escape any ASCII punctuation character, which is then kept as plain text.
+/// Reference links cannot be resolved from a single line, so they are kept as plain text.
+///
+/// # Arguments
+/// * `text` - A single line of Markdown text.
+///
+/// # Examples
+/// ```
+/// # use parser::parse::inline::{parse_inline, Inline};
+///
+/// let inlines = parse_inline("Run `ls` for **more**.");
+/// assert_eq!(
+///     inlines,
+///     vec![
+///         Inline::Text { text: "Run ".to_string() },
+///         Inline::Code { code: "ls".to_string() },
+///         Inline::Text { text: " for ".to_string() },
+///         Inline::Strong { children: vec![Inline::Text { text: "more".to_string() }] },
+///         Inline::Text { text: ".".to_string() },
+///     ]
+/// );
+/// ```
+pub fn parse_inline(text: &str) -> Vec<Inline> {
+    let mut inlines = parse_unresolved_inline(text);
+    resolve_link_references(&mut inlines, &HashMap::new());
+    inlines
+}
+
+/// Returns the inline elements parsed from one line of Markdown text, keeping any references to link definitions.
+/// The references must be resolved with `resolve_link_references` once the definitions are known.
+pub(crate) fn parse_unresolved_inline(text: &str) -> Vec<Inline> {
+    let chars = text.chars().collect::<Vec<char>>();
+    parse_span(&chars)
+}
+
+/// Positions of the delimiters closing those opened at each position of a span, keyed by the opening position and the delimiter's marker and length.
+/// Unmatched openers are stored as `None`, so that each closing delimiter is only searched for once.
+type Closers = HashMap<(usize, char, usize), Option<usize>>;
+
+/// Parse a span of characters into inline elements.
+/// Characters that do not start an inline element are collected as plain text.
+fn parse_span(chars: &[char]) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    let mut text = String::new();
+    let mut closers = Closers::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if let Some((inline, end)) = try_parse_inline(chars, i, &mut closers) {
+            push_text(&mut inlines, &mut text);
+            inlines.push(inline);
+            i = end;
+        } else if is_escape(chars, i) {
+            text.push(chars[i + 1]);
+            i += 2;
+        }
+        // an unmatched run of delimiters is literal text and cannot open a shorter delimiter
+        else if is_delimiter(chars[i]) {
+            let end = run_end(chars, i);
+            text.extend(&chars[i..end]);
+            i = end;
+        } else {
+            text.push(chars[i]);
+            i += 1;
+        }
+    }
+
+    push_text(&mut inlines, &mut text);
+    inlines
+}
+
+/// Move any pending plain text into the list of inline elements.
+fn push_text(inlines: &mut Vec<Inline>, text: &mut String) {
+    if !text.is_empty() {
+        inlines.push(Inline::Text {
+            text: std::mem::take(text),
+        });
+    }
+}
+
+/// Try to parse an inline element starting at the given position.
+/// Return the element and the position directly after it.
+fn try_parse_inline(
+    chars: &[char],
+    start: usize,
+    closers: &mut Closers,
+) -> Option<(Inline, usize)> {
+    match chars[start] {
+        '`' => try_parse_code(chars, start),
+        '$' => try_parse_math(chars, start),
+        '*' => try_parse_strong(chars, start, closers)
+            .or_else(|| try_parse_emphasis(chars, start, closers)),
+        '_' => try_parse_emphasis(chars, start, closers),
+        '~' => try_parse_strikethrough(chars, start, closers),
+        '=' => try_parse_mark(chars, start, closers),
+        '[' => try_parse_footnote_reference(chars, start).or_else(|| try_parse_link(chars, start)),
+        '!' => try_parse_image(chars, start),
+        '<' => try_parse_autolink(chars, start).or_else(|| try_parse_html(chars, start)),
+        'h' => try_parse_bare_url(chars, start),
+        _ => None,
+    }
+}
+
+/// Whether the character at the given position is a backslash escaping ASCII punctuation.
+fn is_escape(chars: &[char], i: usize) -> bool {
+    chars[i] == '\\' && chars.get(i + 1).is_some_and(|c| c.is_ascii_punctuation())
+}
+
+/// Return the position directly after the run of identical characters starting at `start`.
+pub(crate) fn run_end(chars: &[char], start: usize) -> usize {
+    let c = chars[start];
+    chars[start..]
+        .iter()
+        .position(|&next| next != c)
+        .map_or(chars.len(), |len| start + len)
+}
+
+/// Try to parse inline code.
+/// A code span opens with a run of backticks and closes with a run of the same length.
+/// One leading and trailing space is stripped so that backticks can be placed at either end.
+pub(crate) fn try_parse_code(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    let open_end = run_end(chars, start);
+    let length = open_end - start;
+
+    let mut i = open_end;
+    while i < chars.len() {
+        if chars[i] != '`' {
+            i += 1;
+            continue;
+        }
+
+        let close_end = run_end(chars, i);
+        if close_end - i == length {
+            let mut code = chars[open_end..i].iter().collect::<String>();
+            if code.len() > 2
+                && code.starts_with(' ')
+                && code.ends_with(' ')
+                && !code.trim().is_empty()
+            {
+                code = code[1..code.len() - 1].to_string();
+            }
+            return Some((Inline::Code { code }, close_end));
+        }
+        i = close_end;
+    }
+    None
+}
+
+/// Try to parse inline math, which is TeX wrapped in single dollar signs, or double dollar signs for display math.
+/// The dollar signs must be directly next to the TeX, and a single closing dollar sign cannot be followed by a digit, so prices like `$5 and $10` are left as text.
+/// The TeX is kept verbatim, where a backslash stops the next character from closing the math.
+fn try_parse_math(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    let open_end = run_end(chars, start);
+    let length = open_end - start;
+    if length > 2 || chars.get(open_end).is_none_or(|c| c.is_whitespace()) {
+        return None;
+    }
+
+    let display = length == 2;
+    let mut i = open_end;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '$' => {
+                let close_end = run_end(chars, i);
+                if close_end - i == length
+                    && !chars[i - 1].is_whitespace()
+                    && (display || !chars.get(close_end).is_some_and(char::is_ascii_digit))
+                {
+                    let tex = chars[open_end..i].iter().collect();
+                    return Some((Inline::Math { display, tex }, close_end));
+                }
+                i = close_end;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// A pair of identical delimiters which wrap inline content, e.g. the `**` around bold text.
+struct Delimiter {
+    /// Character repeated to form the delimiter.
+    marker: char,
+    /// Number of times the character is repeated.
+    length: usize,
+    /// Whether the delimiter can open or close in the middle of a word.
+    intraword: bool,
+}
+
+const STRONG: Delimiter = Delimiter {
+    marker: '*',
+    length: 2,
+    intraword: true,
+};
+
+const ASTERISK_EMPHASIS: Delimiter = Delimiter {
+    marker: '*',
+    length: 1,
+    intraword: true,
+};
+
+// underscores are common in identifiers such as snake_case so cannot be used inside words
+const UNDERSCORE_EMPHASIS: Delimiter = Delimiter {
+    marker: '_',
+    length: 1,
+    intraword: false,
+};
+
+const STRIKETHROUGH: Delimiter = Delimiter {
+    marker: '~',
+    length: 2,
+    intraword: true,
+};
+
+// equality checks such as x==y are common in programming posts so cannot be used inside words
+const MARK: Delimiter = Delimiter {
+    marker: '=',
+    length: 2,
+    intraword: false,
+};
+
+/// Whether the character can be used to form a delimiter.
+fn is_delimiter(c: char) -> bool {
+    ['`', '$', '*', '_', '~', '='].contains(&c)
+}
+
+/// Try to parse bold text wrapped in double asterisks.
+fn try_parse_strong(
+    chars: &[char],
+    start: usize,
+    closers: &mut Closers,
+) -> Option<(Inline, usize)> {
+    let (children, end) = try_parse_delimited(chars, start, &STRONG, closers)?;
+    Some((Inline::Strong { children }, end))
+}
+
+/// Try to parse italic text wrapped in single asterisks or underscores.
+fn try_parse_emphasis(
+    chars: &[char],
+    start: usize,
+    closers: &mut Closers,
+) -> Option<(Inline, usize)> {
+    let (children, end) = try_parse_delimited(chars, start, emphasis(chars[start]), closers)?;
+    Some((Inline::Emphasis { children }, end))
+}
+
+/// Try to parse strikethrough text wrapped in double tildes.
+fn try_parse_strikethrough(
+    chars: &[char],
+    start: usize,
+    closers: &mut Closers,
+) -> Option<(Inline, usize)> {
+    let (children, end) = try_parse_delimited(chars, start, &STRIKETHROUGH, closers)?;
+    Some((Inline::Strikethrough { children }, end))
+}
+
+/// Try to parse highlighted text wrapped in double equals signs.
+fn try_parse_mark(chars: &[char], start: usize, closers: &mut Closers) -> Option<(Inline, usize)> {
+    let (children, end) = try_parse_delimited(chars, start, &MARK, closers)?;
+    Some((Inline::Mark { children }, end))
+}
+
+/// Return the emphasis delimiter made of the given marker, either an asterisk or an underscore.
+fn emphasis(marker: char) -> &'static Delimiter {
+    match marker {
+        '_' => &UNDERSCORE_EMPHASIS,
+        _ => &ASTERISK_EMPHASIS,
+    }
+}
+
+/// Try to parse the inline elements wrapped by a delimiter opened at `start`.
+/// Return the wrapped elements and the position directly after the closing delimiter.
+fn try_parse_delimited(
+    chars: &[char],
+    start: usize,
+    delimiter: &Delimiter,
+    closers: &mut Closers,
+) -> Option<(Vec<Inline>, usize)> {
+    let close = find_closing_delimiter(chars, start, delimiter, closers)?;
+    let children = parse_span(&chars[start + delimiter.length..close]);
+    Some((children, close + delimiter.length))
+}
+
+/// Find the position of the delimiter closing the one opened at `start`.
+/// Escaped characters and nested inline elements are skipped over so their contents cannot close the delimiter.
+/// The result is remembered, since nested elements are skipped over again by every earlier opener.
+fn find_closing_delimiter(
+    chars: &[char],
+    start: usize,
+    delimiter: &Delimiter,
+    closers: &mut Closers,
+) -> Option<usize> {
+    if !is_opening(chars, start, delimiter) {
+        return None;
+    }
+
+    let key = (start, delimiter.marker, delimiter.length);
+    if let Some(&close) = closers.get(&key) {
+        return close;
+    }
+
+    let content = start + delimiter.length;
+    let mut close = None;
+    let mut i = content;
+    while i < chars.len() {
+        if is_escape(chars, i) {
+            i += 2;
+        } else if i > content && is_closing(chars, i, delimiter) {
+            close = Some(i);
+            break;
+        } else {
+            i = skip_inline(chars, i, closers);
+        }
+    }
+    closers.insert(key, close);
+    close
+}
+
+/// Whether a delimiter opens at `start`.
+/// It must be followed by non-whitespace.
+/// A run of three or more markers can open both the single and double delimiter, e.g. `***` for bold italics.
+fn is_opening(chars: &[char], start: usize, delimiter: &Delimiter) -> bool {
+    let run = run_end(chars, start) - start;
+    let content = start + delimiter.length;
+
+    chars[start] == delimiter.marker
+        && (run == delimiter.length || run > 2)
+        && chars.get(content).is_some_and(|c| !c.is_whitespace())
+        && (delimiter.intraword || start == 0 || !chars[start - 1].is_alphanumeric())
+}
+
+/// Whether a delimiter closes at `i`.
+/// It must be preceded by non-whitespace and may be the start of a longer run of markers.
+fn is_closing(chars: &[char], i: usize, delimiter: &Delimiter) -> bool {
+    let after = i + delimiter.length;
+
+    chars[i] == delimiter.marker
+        && run_end(chars, i) >= after
+        && !chars[i - 1].is_whitespace()
+        && (delimiter.intraword || chars.get(after).is_none_or(|c| !c.is_alphanumeric()))
+}
+
+/// Return the position after the inline element or run of delimiter characters starting at `i`.
+/// Delimited elements are only measured, without parsing the elements inside them.
+fn skip_inline(chars: &[char], i: usize, closers: &mut Closers) -> usize {
+    let end = match chars[i] {
+        '*' => delimited_end(chars, i, &STRONG, closers)
+            .or_else(|| delimited_end(chars, i, &ASTERISK_EMPHASIS, closers)),
+        '_' => delimited_end(chars, i, &UNDERSCORE_EMPHASIS, closers),
+        '~' => delimited_end(chars, i, &STRIKETHROUGH, closers),
+        '=' => delimited_end(chars, i, &MARK, closers),
+        _ => try_parse_inline(chars, i, closers).map(|(_, end)| end),
+    };
+    match end {
+        Some(end) => end,
+        None if is_delimiter(chars[i]) => run_end(chars, i),
+        None => i + 1,
+    }
+}
+
+/// Return the position directly after the delimiter closing the one opened at `start`.
+fn delimited_end(
+    chars: &[char],
+    start: usize,
+    delimiter: &Delimiter,
+    closers: &mut Closers,
+) -> Option<usize> {
+    find_closing_delimiter(chars, start, delimiter, closers).map(|close| close + delimiter.length)
+}
+
+/// Try to parse a link that looks like `[label](href "title")`, where the title is optional.
+/// Brackets and parentheses may be nested inside the label and href as long as they are balanced.
+/// Otherwise try to parse a reference to a link definition, which looks like `[label][reference]`, `[reference][]` or `[reference]`.
+fn try_parse_link(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    let label_end = find_matching(chars, start, '[', ']')?;
+    let label_chars = &chars[start + 1..label_end];
+    let label = parse_span(label_chars);
+
+    match chars.get(label_end + 1) {
+        Some('(') => {
+            let href_end = find_matching(chars, label_end + 1, '(', ')')?;
+            let destination = unescape(&chars[label_end + 2..href_end]);
+            let (href, title) = split_title(destination.trim());
+            let link = Inline::Link {
+                href: href.to_string(),
+                title: title.map(str::to_string),
+                label: remove_links(label),
+            };
+            Some((link, href_end + 1))
+        }
+        Some('[') => {
+            let reference_end = find_matching(chars, label_end + 1, '[', ']')?;
+            let reference_chars = &chars[label_end + 2..reference_end];
+            // a collapsed reference like `[reference][]` uses its label as the reference
+            let reference = if reference_chars.is_empty() {
+                label_chars
+            } else {
+                reference_chars
+            };
+            let reference = Inline::LinkReference {
+                reference: reference.iter().collect(),
+                label,
+                suffix: chars[label_end + 1..=reference_end].iter().collect(),
+            };
+            Some((reference, reference_end + 1))
+        }
+        _ if !label_chars.is_empty() => {
+            let reference = Inline::LinkReference {
+                reference: label_chars.iter().collect(),
+                label,
+                suffix: String::new(),
+            };
+            Some((reference, label_end + 1))
+        }
+        _ => None,
+    }
+}
+
+/// Replace links inside a link's label with their own labels, since links cannot be nested.
+fn remove_links(label: Vec<Inline>) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    for inline in label {
+        match inline {
+            Inline::Link { label, .. } => {
+                for child in remove_links(label) {
+                    match child {
+                        Inline::Text { text } => push_inline_text(&mut inlines, text),
+                        _ => inlines.push(child),
+                    }
+                }
+            }
+            Inline::Text { text } => push_inline_text(&mut inlines, text),
+            _ => inlines.push(inline),
+        }
+    }
+    inlines
+}
+
+/// Try to parse an autolink, which is a URL like `<https://example.com>` or an email address like `<me@example.com>`.
+fn try_parse_autolink(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    let end = start + 1 + chars[start + 1..].iter().position(|&c| c == '>')?;
+    let address = chars[start + 1..end].iter().collect::<String>();
+    if address.is_empty() || address.contains(|c: char| c.is_whitespace() || c == '<') {
+        return None;
+    }
+
+    let href = if has_scheme(&address) {
+        address.clone()
+    } else if is_email(&address) {
+        format!("mailto:{}", address)
+    } else {
+        return None;
+    };
+    Some((url_link(href, address), end + 1))
+}
+
+/// Try to parse a raw HTML tag or comment.
+fn try_parse_html(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    let end =
+        parse_comment(chars, start).or_else(|| parse_tag(chars, start).map(|(_, end)| end))?;
+    let html = chars[start..end].iter().collect();
+    Some((Inline::Html { html }, end))
+}
+
+/// Whether the address starts with a URL scheme like `https:`.
+fn has_scheme(address: &str) -> bool {
+    address.split_once(':').is_some_and(|(scheme, _)| {
+        (2..=32).contains(&scheme.len())
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+    })
+}
+
+/// Whether the address looks like an email address, with a name and a domain containing a dot.
+fn is_email(address: &str) -> bool {
+    address.split_once('@').is_some_and(|(name, domain)| {
+        !name.is_empty()
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && domain.contains('.')
+            && !domain.contains('@')
+    })
+}
+
+/// Try to parse a bare URL starting with `http://` or `https://` that is not part of a word.
+/// Trailing punctuation is left out of the URL, as are closing parentheses without a matching opening parenthesis.
+fn try_parse_bare_url(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    if start > 0 && chars[start - 1].is_alphanumeric() {
+        return None;
+    }
+
+    let rest = &chars[start..];
+    let scheme = ["https://", "http://"]
+        .iter()
+        .map(|scheme| scheme.chars().collect::<Vec<char>>())
+        .find(|scheme| rest.starts_with(scheme))?;
+
+    let mut end = start
+        + rest
+            .iter()
+            .position(|&c| c.is_whitespace() || c == '<')
+            .unwrap_or(rest.len());
+    loop {
+        let url = &chars[start..end];
+        let trailing_paren = url.last() == Some(&')')
+            && url.iter().filter(|&&c| c == ')').count()
+                > url.iter().filter(|&&c| c == '(').count();
+        if trailing_paren || url.last().is_some_and(|c| "?!.,:;*_~'\"".contains(*c)) {
+            end -= 1;
+        } else {
+            break;
+        }
+    }
+
+    if end - start <= scheme.len() {
+        return None;
+    }
+    let url = chars[start..end].iter().collect::<String>();
+    Some((url_link(url.clone(), url), end))
+}
+
+/// Create a link whose label is the URL itself.
+fn url_link(href: String, text: String) -> Inline {
+    Inline::Link {
+        href,
+        title: None,
+        label: vec![Inline::Text { text }],
+    }
+}
+
+/// A link definition like `[reference]: href "title"`.
+pub(crate) struct LinkDefinition {
+    /// Destination of links using the definition
+    pub(crate) href: String,
+    /// Optional text shown when hovering over links using the definition
+    pub(crate) title: Option<String>,
+}
+
+/// Try to parse a link definition from the text following `[reference]:`.
+pub(crate) fn parse_link_definition(text: &str) -> Option<LinkDefinition> {
+    let (href, title) = split_title(text.trim());
+    let href = href
+        .strip_prefix('<')
+        .and_then(|h| h.strip_suffix('>'))
+        .unwrap_or(href);
+    if href.is_empty() || href.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(LinkDefinition {
+        href: href.to_string(),
+        title: title.map(str::to_string),
+    })
+}
+
+/// Normalise a reference so that references match definitions ignoring case and repeated whitespace.
+pub(crate) fn normalise_reference(reference: &str) -> String {
+    reference
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Sanitise the raw HTML in the inline elements, including HTML nested inside other inline elements.
+/// Tags that are not allowed become plain text, so text like `Vec<int>` is shown as it was written.
+pub(crate) fn sanitize_inline_html(inlines: &mut Vec<Inline>, sanitizer: &HtmlSanitizer) {
+    for mut inline in std::mem::take(inlines) {
+        if let Inline::Strong { children }
+        | Inline::Emphasis { children }
+        | Inline::Strikethrough { children }
+        | Inline::Mark { children }
+        | Inline::Link {
+            label: children, ..
+        }
+        | Inline::LinkReference {
+            label: children, ..
+        } = &mut inline
+        {
+            sanitize_inline_html(children, sanitizer);
+        }
+
+        match inline {
+            Inline::Html { html } => match sanitizer.sanitize_tag(&html) {
+                Some(html) => inlines.push(Inline::Html { html }),
+                None => push_inline_text(inlines, html),
+            },
+            Inline::Text { text } => push_inline_text(inlines, text),
+            inline => inlines.push(inline),
+        }
+    }
+}
+
+/// Replace references to link definitions with links, including references nested inside other inline elements.
+/// References to undefined links are kept as plain text, and a warning is logged for full and collapsed references.
+/// The definitions must be keyed by their normalised reference.
+pub(crate) fn resolve_link_references(
+    inlines: &mut Vec<Inline>,
+    definitions: &HashMap<String, LinkDefinition>,
+) {
+    for inline in std::mem::take(inlines) {
+        match inline {
+            Inline::LinkReference {
+                reference,
+                mut label,
+                suffix,
+            } => {
+                resolve_link_references(&mut label, definitions);
+                match definitions.get(&normalise_reference(&reference)) {
+                    Some(definition) => inlines.push(Inline::Link {
+                        href: definition.href.clone(),
+                        title: definition.title.clone(),
+                        label: remove_links(label),
+                    }),
+                    None => {
+                        // shortcut references are often just text in brackets
+                        if !suffix.is_empty() {
+                            warn!("link reference {} is not defined", reference);
+                        }
+                        push_inline_text(inlines, "[".to_string());
+                        for child in label {
+                            match child {
+                                Inline::Text { text } => push_inline_text(inlines, text),
+                                _ => inlines.push(child),
+                            }
+                        }
+                        push_inline_text(inlines, format!("]{}", suffix));
+                    }
+                }
+            }
+            Inline::Strong { mut children } => {
+                resolve_link_references(&mut children, definitions);
+                inlines.push(Inline::Strong { children });
+            }
+            Inline::Emphasis { mut children } => {
+                resolve_link_references(&mut children, definitions);
+                inlines.push(Inline::Emphasis { children });
+            }
+            Inline::Strikethrough { mut children } => {
+                resolve_link_references(&mut children, definitions);
+                inlines.push(Inline::Strikethrough { children });
+            }
+            Inline::Mark { mut children } => {
+                resolve_link_references(&mut children, definitions);
+                inlines.push(Inline::Mark { children });
+            }
+            // links cannot be nested, so references inside a link's label are left as text
+            Inline::Link {
+                href,
+                title,
+                mut label,
+            } => {
+                resolve_link_references(&mut label, definitions);
+                inlines.push(Inline::Link {
+                    href,
+                    title,
+                    label: remove_links(label),
+                });
+            }
+            Inline::Text { text } => push_inline_text(inlines, text),
+            _ => inlines.push(inline),
+        }
+    }
+}
+
+/// Add text to the end of the inline elements, joining it onto any text already at the end.
+fn push_inline_text(inlines: &mut Vec<Inline>, text: String) {
+    match inlines.last_mut() {
+        Some(Inline::Text { text: last }) => last.push_str(&text),
+        _ => inlines.push(Inline::Text { text }),
+    }
+}
+
+/// Try to parse a footnote reference that looks like `[^label]`, where the label has no whitespace.
+/// A reference followed by an href is a link instead.
+fn try_parse_footnote_reference(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    if chars.get(start + 1) != Some(&'^') {
+        return None;
+    }
+    let end = start + 2 + chars[start + 2..].iter().position(|&c| c == ']')?;
+    let label = &chars[start + 2..end];
+    if label.is_empty()
+        || label.iter().any(|c| c.is_whitespace() || *c == '[')
+        || chars.get(end + 1) == Some(&'(')
+    {
+        return None;
+    }
+
+    let reference = Inline::FootnoteReference {
+        label: label.iter().collect(),
+        number: 0,
+        occurrence: 0,
+    };
+    Some((reference, end + 1))
+}
+
+/// Try to parse an image that looks like `![alt](src "title")`, where the title is optional.
+fn try_parse_image(chars: &[char], start: usize) -> Option<(Inline, usize)> {
+    if chars.get(start + 1) != Some(&'[') {
+        return None;
+    }
+    let alt_end = find_matching(chars, start + 1, '[', ']')?;
+    if chars.get(alt_end + 1) != Some(&'(') {
+        return None;
+    }
+    let src_end = find_matching(chars, alt_end + 1, '(', ')')?;
+
+    let alt = plain_text(&parse_span(&chars[start + 2..alt_end]));
+    let destination = unescape(&chars[alt_end + 2..src_end]);
+    let (src, title) = split_title(destination.trim());
+    let image = Image {
+        src: src.to_string(),
+        alt,
+        title: title.map(str::to_string),
+        width: None,
+        height: None,
+    };
+    Some((Inline::Image(image), src_end + 1))
+}
+
+/// Split a destination like `src "title"` into the source and the optional quoted title.
+fn split_title(destination: &str) -> (&str, Option<&str>) {
+    destination
+        .strip_suffix('"')
+        .and_then(|rest| rest.rsplit_once(" \""))
+        .map_or((destination, None), |(src, title)| {
+            (src.trim_end(), Some(title))
+        })
+}
+
+/// Return the text of inline elements with all formatting removed.
+fn plain_text(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text { text } => text.clone(),
+            Inline::Code { code } => code.clone(),
+            Inline::Math { tex, .. } => tex.clone(),
+            Inline::Strong { children }
+            | Inline::Emphasis { children }
+            | Inline::Strikethrough { children }
+            | Inline::Mark { children }
+            | Inline::Link {
+                label: children, ..
+            }
+            | Inline::LinkReference {
+                label: children, ..
+            } => plain_text(children),
+            Inline::Image(image) => image.alt.clone(),
+            Inline::FootnoteReference { .. } | Inline::Html { .. } => String::new(),
+        })
+        .collect()
+}
+
+/// Find the position of the bracket closing the one at `start`, skipping escapes and code spans.
+fn find_matching(chars: &[char], start: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        if is_escape(chars, i) {
+            i += 2;
+            continue;
+        }
+
+        if chars[i] == '`' {
+            i = try_parse_code(chars, i).map_or(run_end(chars, i), |(_, end)| end);
+            continue;
+        }
+
+        if chars[i] == open {
+            depth += 1;
+        } else if chars[i] == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Collect characters into a string with escaping backslashes removed.
+fn unescape(chars: &[char]) -> String {
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if is_escape(chars, i) {
+            i += 1;
+        }
+        text.push(chars[i]);
+        i += 1;
+    }
+    text
+}
+
+/// Unit tests for the inline parser.
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::parse::inline::{
+        normalise_reference, parse_inline, parse_unresolved_inline, resolve_link_references, Image,
+        Inline, LinkDefinition,
+    };
+
+    fn text(text: &str) -> Inline {
+        Inline::Text {
+            text: text.to_string(),
+        }
+    }
+
+    fn code(code: &str) -> Inline {
+        Inline::Code {
+            code: code.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(parse_inline("The big car."), vec![text("The big car.")]);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(parse_inline(""), vec![]);
+    }
+
+    #[test]
+    fn test_link_in_middle() {
+        assert_eq!(
+            parse_inline(
+                "There is info at [this link](https://www.google.com) for some more information"
+            ),
+            vec![
+                text("There is info at "),
+                Inline::Link {
+                    href: "https://www.google.com".to_string(),
+                    title: None,
+                    label: vec![text("this link")],
+                },
+                text(" for some more information"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_with_parentheses_in_href() {
+        assert_eq!(
+            parse_inline("[wiki](https://en.wikipedia.org/wiki/Rust_(programming_language))"),
+            vec![Inline::Link {
+                href: "https://en.wikipedia.org/wiki/Rust_(programming_language)".to_string(),
+                title: None,
+                label: vec![text("wiki")],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_link_with_code_label() {
+        assert_eq!(
+            parse_inline("see [`man find`](https://man7.org/linux/man-pages/man1/find.1.html)"),
+            vec![
+                text("see "),
+                Inline::Link {
+                    href: "https://man7.org/linux/man-pages/man1/find.1.html".to_string(),
+                    title: None,
+                    label: vec![code("man find")],
+                },
+            ]
+        );
+    }
+
+    /// Test an image with alt text and a title.
+    #[test]
+    fn test_image() {
+        assert_eq!(
+            parse_inline(r#"see ![a *red* circle](images/circle.png "Circle") here"#),
+            vec![
+                text("see "),
+                Inline::Image(Image {
+                    src: "images/circle.png".to_string(),
+                    alt: "a red circle".to_string(),
+                    title: Some("Circle".to_string()),
+                    width: None,
+                    height: None,
+                }),
+                text(" here"),
+            ]
+        );
+    }
+
+    /// Test an image without a title, and an exclamation mark that does not start an image.
+    #[test]
+    fn test_image_without_title() {
+        assert_eq!(
+            parse_inline("Wow! ![](a b.png)"),
+            vec![
+                text("Wow! "),
+                Inline::Image(Image {
+                    src: "a b.png".to_string(),
+                    alt: "".to_string(),
+                    title: None,
+                    width: None,
+                    height: None,
+                }),
+            ]
+        );
+    }
+
+    /// Test footnote references, which are not numbered until the whole document is parsed.
+    #[test]
+    fn test_footnote_reference() {
+        assert_eq!(
+            parse_inline("source[^1] and [^ not] and [^2](link)"),
+            vec![
+                text("source"),
+                Inline::FootnoteReference {
+                    label: "1".to_string(),
+                    number: 0,
+                    occurrence: 0,
+                },
+                text(" and [^ not] and "),
+                Inline::Link {
+                    href: "link".to_string(),
+                    title: None,
+                    label: vec![text("^2")],
+                },
+            ]
+        );
+    }
+
+    /// Test an inline link with a title.
+    #[test]
+    fn test_link_with_title() {
+        assert_eq!(
+            parse_inline(r#"[docs](https://docs.rs "Rust docs")"#),
+            vec![Inline::Link {
+                href: "https://docs.rs".to_string(),
+                title: Some("Rust docs".to_string()),
+                label: vec![text("docs")],
+            }]
+        );
+    }
+
+    /// Test full, collapsed and shortcut references, which are matched ignoring case.
+    #[test]
+    fn test_link_references() {
+        let definitions = HashMap::from([(
+            normalise_reference("Rust  Docs"),
+            LinkDefinition {
+                href: "https://docs.rs".to_string(),
+                title: None,
+            },
+        )]);
+        let link = |label: Inline| Inline::Link {
+            href: "https://docs.rs".to_string(),
+            title: None,
+            label: vec![label],
+        };
+
+        let mut inlines = parse_unresolved_inline(
+            "[*see*][rust docs], [Rust docs][], [rust docs] or [rust][none]",
+        );
+        resolve_link_references(&mut inlines, &definitions);
+        assert_eq!(
+            inlines,
+            vec![
+                link(Inline::Emphasis {
+                    children: vec![text("see")]
+                }),
+                text(", "),
+                link(text("Rust docs")),
+                text(", "),
+                link(text("rust docs")),
+                text(" or [rust][none]"),
+            ]
+        );
+    }
+
+    /// Test autolinks for URLs and email addresses, which must not contain spaces.
+    #[test]
+    fn test_autolinks() {
+        let link = |href: &str, label: &str| Inline::Link {
+            href: href.to_string(),
+            title: None,
+            label: vec![text(label)],
+        };
+        assert_eq!(
+            parse_inline("<https://example.com/a_b> or <me@example.com>, not <me@example> or <a b"),
+            vec![
+                link("https://example.com/a_b", "https://example.com/a_b"),
+                text(" or "),
+                link("mailto:me@example.com", "me@example.com"),
+                text(", not <me@example> or <a b"),
+            ]
+        );
+    }
+
+    /// Test bare URLs, which leave out trailing punctuation and unbalanced parentheses.
+    #[test]
+    fn test_bare_urls() {
+        let link = |url: &str| Inline::Link {
+            href: url.to_string(),
+            title: None,
+            label: vec![text(url)],
+        };
+        assert_eq!(
+            parse_inline("See https://en.wikipedia.org/wiki/Rust_(programming_language). (also http://example.com/a?b=c)"),
+            vec![
+                text("See "),
+                link("https://en.wikipedia.org/wiki/Rust_(programming_language)"),
+                text(". (also "),
+                link("http://example.com/a?b=c"),
+                text(")"),
+            ]
+        );
+        assert_eq!(
+            parse_inline("xhttps://example.com https:// [https://a.com](https://a.com)"),
+            vec![
+                text("xhttps://example.com https:// "),
+                Inline::Link {
+                    href: "https://a.com".to_string(),
+                    title: None,
+                    label: vec![text("https://a.com")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_brackets_without_href() {
+        assert_eq!(
+            parse_inline("an array [1, 2] (of ints)"),
+            vec![text("an array [1, 2] (of ints)")]
+        );
+    }
+
+    #[test]
+    fn test_multiple_code() {
+        assert_eq!(
+            parse_inline("When using `upper_bound` and `lower_bound` from `<algorithm>`."),
+            vec![
+                text("When using "),
+                code("upper_bound"),
+                text(" and "),
+                code("lower_bound"),
+                text(" from "),
+                code("<algorithm>"),
+                text("."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_with_backticks() {
+        assert_eq!(
+            parse_inline("type `` ` `` to quote"),
+            vec![text("type "), code("`"), text(" to quote")]
+        );
+    }
+
+    #[test]
+    fn test_code_is_not_parsed() {
+        assert_eq!(
+            parse_inline("`**not bold** [or](link)`"),
+            vec![code("**not bold** [or](link)")]
+        );
+    }
+
+    #[test]
+    fn test_unmatched_backticks() {
+        assert_eq!(parse_inline("``a`"), vec![text("``a`")]);
+    }
+
+    #[test]
+    fn test_strong() {
+        assert_eq!(
+            parse_inline("this is **very important** to know"),
+            vec![
+                text("this is "),
+                Inline::Strong {
+                    children: vec![text("very important")]
+                },
+                text(" to know"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strong_with_nested_code() {
+        assert_eq!(
+            parse_inline("**never use `**` here**"),
+            vec![Inline::Strong {
+                children: vec![text("never use "), code("**"), text(" here")]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_strong_needs_flanking_text() {
+        assert_eq!(parse_inline("2 ** 3 ** 4"), vec![text("2 ** 3 ** 4")]);
+    }
+
+    #[test]
+    fn test_escaped_brackets() {
+        assert_eq!(
+            parse_inline("Source \\[1\\] recommends `$PATH`"),
+            vec![text("Source [1] recommends "), code("$PATH")]
+        );
+    }
+
+    #[test]
+    fn test_escaped_backtick_and_asterisk() {
+        assert_eq!(
+            parse_inline("the backtic \\` and asterisk \\*\\* look like this"),
+            vec![text("the backtic ` and asterisk ** look like this")]
+        );
+    }
+
+    #[test]
+    fn test_backslash_not_escaping() {
+        assert_eq!(parse_inline("C:\\Users"), vec![text("C:\\Users")]);
+    }
+
+    #[test]
+    fn test_emphasis() {
+        assert_eq!(
+            parse_inline("*either* or _both_"),
+            vec![
+                Inline::Emphasis {
+                    children: vec![text("either")]
+                },
+                text(" or "),
+                Inline::Emphasis {
+                    children: vec![text("both")]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_underscore_inside_word() {
+        assert_eq!(
+            parse_inline("call snake_case_name or __init__"),
+            vec![text("call snake_case_name or __init__")]
+        );
+    }
+
+    #[test]
+    fn test_emphasis_inside_strong() {
+        assert_eq!(
+            parse_inline("**bold *italic* bold**"),
+            vec![Inline::Strong {
+                children: vec![
+                    text("bold "),
+                    Inline::Emphasis {
+                        children: vec![text("italic")]
+                    },
+                    text(" bold"),
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_strong_inside_emphasis() {
+        assert_eq!(
+            parse_inline("*italic **bold** italic*"),
+            vec![Inline::Emphasis {
+                children: vec![
+                    text("italic "),
+                    Inline::Strong {
+                        children: vec![text("bold")]
+                    },
+                    text(" italic"),
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_strong_emphasis_shared_delimiters() {
+        assert_eq!(
+            parse_inline("***both***"),
+            vec![Inline::Strong {
+                children: vec![Inline::Emphasis {
+                    children: vec![text("both")]
+                }]
+            }]
+        );
+        assert_eq!(
+            parse_inline("*italic **bold***"),
+            vec![Inline::Emphasis {
+                children: vec![
+                    text("italic "),
+                    Inline::Strong {
+                        children: vec![text("bold")]
+                    },
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_emphasis_cannot_close_inside_code() {
+        assert_eq!(
+            parse_inline("*use `a*b` here*"),
+            vec![Inline::Emphasis {
+                children: vec![text("use "), code("a*b"), text(" here")]
+            }]
+        );
+        assert_eq!(parse_inline("`*not italic*`"), vec![code("*not italic*")]);
+    }
+
+    #[test]
+    fn test_strikethrough() {
+        assert_eq!(
+            parse_inline("~~wrong~~ right in ~/.zshrc"),
+            vec![
+                Inline::Strikethrough {
+                    children: vec![text("wrong")]
+                },
+                text(" right in ~/.zshrc"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mark() {
+        assert_eq!(
+            parse_inline("==always== quote with **==care==**"),
+            vec![
+                Inline::Mark {
+                    children: vec![text("always")]
+                },
+                text(" quote with "),
+                Inline::Strong {
+                    children: vec![Inline::Mark {
+                        children: vec![text("care")]
+                    }]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mark_not_inside_word() {
+        assert_eq!(
+            parse_inline("if x==y and y==z"),
+            vec![text("if x==y and y==z")]
+        );
+    }
+
+    #[test]
+    fn test_escaped_emphasis() {
+        assert_eq!(
+            parse_inline("\\*not italic\\* or \\~~struck\\~~"),
+            vec![text("*not italic* or ~~struck~~")]
+        );
+    }
+
+    #[test]
+    fn test_math() {
+        assert_eq!(
+            parse_inline(r"$lo \le i < hi$ and $$\sum_{i=0}^{n} a_i$$"),
+            vec![
+                Inline::Math {
+                    display: false,
+                    tex: r"lo \le i < hi".to_string()
+                },
+                text(" and "),
+                Inline::Math {
+                    display: true,
+                    tex: r"\sum_{i=0}^{n} a_i".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            parse_inline(r"$**not bold** \$ x_1$"),
+            vec![Inline::Math {
+                display: false,
+                tex: r"**not bold** \$ x_1".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_math_not_prices() {
+        assert_eq!(
+            parse_inline("costs $5 or $10, and \\$x\\$"),
+            vec![text("costs $5 or $10, and $x$")]
+        );
+    }
+
+    #[test]
+    fn test_many_unmatched_delimiters() {
+        for unmatched in ["*p, ", "**a ", "~~a ", "_p, ", "==a "] {
+            let text = unmatched.repeat(300);
+            assert_eq!(parse_inline(&text), vec![self::text(&text)]);
+        }
+    }
+}
@@ -5,3 +5,6 @@ pub const MARKDOWN: &str = "../blog/lang";
 
 /// JSON dump of parsed blogs.
 pub const JSON: &str = "../website/src/blog-lang.json";
+
+/// Public folder of the website, where images referenced by blogs are copied.
+pub const PUBLIC: &str = "../website/public";
@@ -1,25 +1,201 @@
 //! This module provides a parser for converting Markdown text into HTML elements.
 
-use crate::parse::html_element::HTMLElement;
+use crate::parse::html::{parse_tag, HtmlSanitizer};
+use crate::parse::html_element::{
+    for_each_element, for_each_inline, for_each_line, Alignment, CalloutKind, DefinitionItem,
+    DiagramKind, Footnote, HTMLElement, LineRange, ListItem,
+};
+use crate::parse::inline::{
+    normalise_reference, parse_link_definition, parse_unresolved_inline, resolve_link_references,
+    run_end, sanitize_inline_html, try_parse_code, Inline, LinkDefinition,
+};
+
+use log::warn;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 
 /// Represents the current parsing region.
 enum Region {
     /// Flag indicating that no region is currently being parsed.
     NotSet,
-    /// Represents a code block with an optional language.
-    Code(String, Vec<String>),
+    /// Represents a code block with its opening fence and the info string after it.
+    Code(Fence, String, Vec<String>),
+    /// Represents a code block where every line is indented by four spaces, which have been removed.
+    IndentedCode(Vec<String>),
     /// Represents an ordered list.
-    OrderedList(Vec<String>),
+    OrderedList(RawList),
     /// Represents an unordered list.
-    UnorderedList(Vec<String>),
-    /// Represents a HTML table.
-    Table(Vec<String>, Vec<Vec<String>>, bool),
+    UnorderedList(RawList),
+    /// Represents the header row of a table, which is a paragraph unless the next line is a separator row.
+    TableHeader(String),
+    /// Represents a HTML table with its headers, column alignment and rows.
+    Table(Vec<String>, Vec<Option<Alignment>>, Vec<Vec<String>>),
     /// Represents a paragraph block.
     Paragraph(Vec<String>),
+    /// Represents a blockquote with its leading `'>'` markers stripped.
+    Blockquote(Vec<String>),
+    /// Represents raw HTML, with the text ending the block or `None` if the block ends at a blank line.
+    Html(Vec<String>, Option<String>),
+    /// Represents a collapsible section whose lines are yet to be parsed.
+    Details(RawDetails),
+    /// Represents a definition list whose definitions are yet to be parsed.
+    DefinitionList(RawDefinitionList),
+    /// Represents a display math block with its opening `$$` removed.
+    Math(Vec<String>),
+}
+
+/// Represents a collapsible section like `:::details Summary` that ends at a line of `:::`.
+struct RawDetails {
+    /// Text after the opening marker, shown when the section is collapsed.
+    summary: String,
+    /// Number of nested sections that are still open.
+    depth: usize,
+    /// Fence of a code block inside the section, which cannot close the section.
+    fence: Option<Fence>,
+    /// Lines inside the section.
+    lines: Vec<String>,
+}
+
+/// Marker which opens and closes a collapsible section.
+const DETAILS_MARKER: &str = ":::";
+
+/// Represents a definition list, where each term is followed by lines like `: definition`.
+struct RawDefinitionList {
+    /// Terms of the list along with their definitions.
+    items: Vec<RawDefinitionItem>,
+    /// Line after the list, which is the next term if it is followed by a definition and a paragraph otherwise.
+    term: Option<String>,
+}
+
+/// Represents a term whose definitions are yet to be parsed.
+struct RawDefinitionItem {
+    /// Text of the term.
+    term: String,
+    /// Lines of each definition, with the leading `': '` or indentation removed.
+    definitions: Vec<Vec<String>>,
+}
+
+/// Indentation of lines continuing a definition, which is the width of the `': '` marker.
+const DEFINITION_INDENT: usize = 2;
+
+/// Marker which opens and closes a display math block.
+const MATH_MARKER: &str = "$$";
+
+/// Tags which start a HTML block that ends at a blank line.
+const HTML_BLOCK_TAGS: [&str; 38] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "caption",
+    "center",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+/// Tags which start a HTML block that ends at their closing tag, since their contents can hold blank lines.
+const HTML_RAW_TAGS: [&str; 4] = ["pre", "script", "style", "textarea"];
+
+/// Represents the opening fence of a code block.
+struct Fence {
+    /// Character the fence is made of, either a backtick or a tilde.
+    marker: char,
+    /// Number of characters in the fence - the closing fence must be at least as long.
+    length: usize,
+    /// Number of spaces the fence is indented by, which are also removed from the code.
+    indent: usize,
+}
+
+/// Indentation of every line of an indented code block.
+const CODE_INDENT: usize = 4;
+
+/// Minimum number of characters in a code fence.
+const MIN_FENCE_LENGTH: usize = 3;
+
+/// Represents a list whose items are yet to be parsed.
+struct RawList {
+    /// Number of spaces the list markers are indented by.
+    indent: usize,
+    /// Items of the list.
+    items: Vec<RawListItem>,
+    /// Whether the previous line was blank.
+    after_blank: bool,
+    /// Whether any items are separated by blank lines.
+    loose: bool,
+}
+
+/// Represents a list item whose nested lines are yet to be parsed.
+struct RawListItem {
+    /// Character which ends the list marker - items of the same list must share this character.
+    marker: char,
+    /// Number of an ordered list item, or `None` for unordered list items.
+    number: Option<usize>,
+    /// Column where the text after the list marker starts.
+    column: usize,
+    /// Text after the list marker.
+    text: String,
+    /// Whether a task list checkbox is ticked, or `None` if the item has no checkbox.
+    checked: Option<bool>,
+    /// Lines indented under the item, with the indentation up to `column` removed.
+    nested: Vec<String>,
+}
+
+/// Minimum extra indentation of a line, relative to the list markers, for it to be nested under a list item.
+const MIN_NESTED_INDENT: usize = 2;
+
+/// Maximum number of digits in an ordered list item's number.
+const MAX_ORDERED_DIGITS: usize = 9;
+
+/// Maximum level of a header, which is the number of leading `'#'` characters.
+const MAX_HEADER_LEVEL: usize = 6;
+
+/// Represents a footnote definition that has not been referenced yet.
+struct RawFootnote {
+    /// Label of the footnote, e.g. `note` for `[^note]: text`.
+    label: String,
+    /// Parsed elements of the footnote's text.
+    children: Vec<HTMLElement>,
 }
 
+/// Maximum indentation of a line that can start a new region.
+const MAX_REGION_INDENT: usize = 3;
+
 /// Returns a list of HTML elements parsed from the provided Markdown text.
 /// Parsed paragraph lines have their leading and trailing whitespace stripped.
+/// Paragraphs, list items and table cells are further parsed into inline elements.
+/// Footnote definitions are removed from the text and collected into a `Footnotes` element at the end.
+/// Link definitions are also removed, and references to them are replaced with links.
+/// Raw HTML is passed through the default `HtmlSanitizer`.
 ///
 /// # Arguments
 /// * `text` - A vector of strings containing Markdown text.
@@ -28,6 +204,7 @@ enum Region {
 /// ```
 /// # use parser::parse::to_html::parse_markdown;
 /// # use parser::parse::html_element::HTMLElement;
+/// # use parser::parse::inline::Inline;
 ///
 /// let markdown = vec![
 ///     "# Header".to_string(),
@@ -37,61 +214,142 @@ enum Region {
 /// let elements = parse_markdown(&markdown);
 /// assert_eq!(elements.len(), 2);
 /// assert_eq!(elements[0], HTMLElement::Header { level: 1, content: "Header".to_string() });
-/// assert_eq!(elements[1], HTMLElement::Paragraph { lines: vec!["This is a paragraph.".to_string()] });
+/// assert_eq!(
+///     elements[1],
+///     HTMLElement::Paragraph {
+///         lines: vec![vec![Inline::Text { text: "This is a paragraph.".to_string() }]]
+///     }
+/// );
 /// ```
-pub fn parse_markdown(text: &Vec<String>) -> Vec<HTMLElement> {
+pub fn parse_markdown(text: &[String]) -> Vec<HTMLElement> {
+    parse_markdown_with_sanitizer(text, &HtmlSanitizer::default())
+}
+
+/// Returns a list of HTML elements parsed from the provided Markdown text, like `parse_markdown`.
+/// Raw HTML is passed through the given sanitizer, which controls the allowed tags and attributes.
+///
+/// # Arguments
+/// * `text` - A vector of strings containing Markdown text.
+/// * `sanitizer` - The allow-list of HTML tags and attributes.
+pub fn parse_markdown_with_sanitizer(
+    text: &[String],
+    sanitizer: &HtmlSanitizer,
+) -> Vec<HTMLElement> {
+    let (text, mut footnotes, links) = extract_definitions(text);
+    let mut elements = parse_blocks(&text).0;
+
+    let resolve = &mut |line: &mut Vec<Inline>| resolve_link_references(line, &links);
+    for_each_line(&mut elements, resolve);
+    for footnote in &mut footnotes {
+        for_each_line(&mut footnote.children, resolve);
+    }
+
+    number_footnotes(&mut elements, footnotes);
+
+    for_each_element(&mut elements, &mut |element| {
+        if let HTMLElement::HtmlBlock { html } = element {
+            *html = sanitizer.sanitize(html);
+        }
+    });
+    for_each_line(&mut elements, &mut |line| {
+        sanitize_inline_html(line, sanitizer)
+    });
+    elements
+}
+
+/// Returns a list of HTML elements parsed from the provided Markdown text.
+/// Also return whether any two of the elements are separated by a blank line.
+fn parse_blocks(text: &[String]) -> (Vec<HTMLElement>, bool) {
     let mut region = Region::NotSet;
     let mut elements: Vec<HTMLElement> = Vec::new();
+    // number of elements before the first blank line found between regions
+    let mut before_blank = None;
 
     for line in text {
         // end the current region
-        if line.is_empty() {
+        if line.trim().is_empty() {
             region = handle_blank_line(line, region, &mut elements);
+            if matches!(region, Region::NotSet) && !elements.is_empty() {
+                before_blank.get_or_insert(elements.len());
+            }
             continue;
         }
 
         region = match region {
             Region::NotSet => handle_entering_region(line, &mut elements),
-            Region::Code(lang, lines) => handle_in_code_region(line, &mut elements, lang, lines),
+            Region::Code(fence, lang, lines) => {
+                handle_in_code_region(line, &mut elements, fence, lang, lines)
+            }
+            Region::IndentedCode(lines) => {
+                handle_in_indented_code_region(line, &mut elements, lines)
+            }
             Region::OrderedList(list) => handle_in_ordered_list_region(line, &mut elements, list),
             Region::UnorderedList(list) => {
                 handle_in_unordered_list_region(line, &mut elements, list)
             }
-            Region::Paragraph(lines) => handle_in_paragraph_region(line, lines),
-            Region::Table(headers, rows, is_separator) => {
-                handle_in_table_region(line, &mut elements, headers, rows, is_separator)
+            Region::Paragraph(lines) => handle_in_paragraph_region(line, &mut elements, lines),
+            Region::TableHeader(header) => {
+                handle_in_table_header_region(line, &mut elements, header)
+            }
+            Region::Table(headers, alignment, rows) => {
+                handle_in_table_region(line, &mut elements, headers, alignment, rows)
             }
+            Region::Blockquote(lines) => handle_in_blockquote_region(line, &mut elements, lines),
+            Region::Html(lines, end) => handle_in_html_region(line, &mut elements, lines, end),
+            Region::Details(details) => handle_in_details_region(line, &mut elements, details),
+            Region::DefinitionList(list) => {
+                handle_in_definition_list_region(line, &mut elements, list)
+            }
+            Region::Math(lines) => handle_in_math_region(line, &mut elements, lines),
         };
     }
-    handle_last_region(region, &mut elements);
-    elements
+    close_region(region, &mut elements);
+
+    let separated = before_blank.is_some_and(|count| elements.len() > count);
+    (elements, separated)
 }
 
 /// Handle a blank line for the current region.
 /// Return what the new region should be set as.
-fn handle_blank_line(line: &String, region: Region, elements: &mut Vec<HTMLElement>) -> Region {
+fn handle_blank_line(line: &str, region: Region, elements: &mut Vec<HTMLElement>) -> Region {
     match region {
-        Region::NotSet => region,
         // code should keep blank lines
-        Region::Code(lang, mut code) => {
-            code.push(line.to_string());
-            Region::Code(lang, code)
+        Region::Code(fence, lang, mut code) => {
+            code.push(dedent(line, fence.indent));
+            Region::Code(fence, lang, code)
         }
-        Region::OrderedList(list) => {
-            elements.push(HTMLElement::OrderedList { list });
-            Region::NotSet
+        Region::IndentedCode(mut code) => {
+            code.push(dedent(line, CODE_INDENT));
+            Region::IndentedCode(code)
         }
-        Region::UnorderedList(list) => {
-            elements.push(HTMLElement::UnorderedList { list });
-            Region::NotSet
+        // math is kept verbatim
+        Region::Math(mut tex) => {
+            tex.push(String::new());
+            Region::Math(tex)
         }
-        // table has its own syntax for detecting the end of a region
-        Region::Table(headers, rows, _) => {
-            elements.push(HTMLElement::Table { headers, rows });
-            Region::NotSet
+        // lists can continue after a blank line
+        Region::OrderedList(list) => Region::OrderedList(push_blank_line(list)),
+        Region::UnorderedList(list) => Region::UnorderedList(push_blank_line(list)),
+        // collapsible sections can hold blank lines between their elements
+        Region::Details(mut details) => {
+            details.lines.push(String::new());
+            Region::Details(details)
         }
-        Region::Paragraph(lines) => {
-            elements.push(HTMLElement::Paragraph { lines });
+        // definitions can continue after a blank line, unless a term is left without a definition
+        Region::DefinitionList(mut list) if list.term.is_none() => {
+            if let Some(definition) = last_definition(&mut list) {
+                definition.push(String::new());
+            }
+            Region::DefinitionList(list)
+        }
+        // HTML blocks ending at a closing tag can hold blank lines
+        Region::Html(mut lines, Some(end)) => {
+            lines.push(line.to_string());
+            Region::Html(lines, Some(end))
+        }
+        // every other region ends at a blank line
+        _ => {
+            close_region(region, elements);
             Region::NotSet
         }
     }
@@ -99,41 +357,60 @@ fn handle_blank_line(line: &String, region: Region, elements: &mut Vec<HTMLEleme
 
 /// Handle the entering of a new region.
 /// Return what the new region should be.
-fn handle_entering_region(line: &String, elements: &mut Vec<HTMLElement>) -> Region {
+fn handle_entering_region(line: &str, elements: &mut Vec<HTMLElement>) -> Region {
+    // indented code
+    if indentation(line) >= CODE_INDENT {
+        Region::IndentedCode(vec![dedent(line, CODE_INDENT)])
+    }
     // header
-    if line.starts_with('#') {
-        let content = line.trim_start_matches('#').trim_start().to_string();
-        let level = line.find(|c| c != '#').unwrap_or(0);
+    else if let Some((level, content)) = parse_header(line) {
         elements.push(HTMLElement::Header { level, content });
         Region::NotSet
     }
     // code
-    else if line.starts_with("```") {
-        let lang = line.trim_start_matches("```").trim().to_string();
-        Region::Code(lang, Vec::new())
+    else if let Some((fence, lang)) = parse_fence(line) {
+        Region::Code(fence, lang.to_string(), Vec::new())
     }
-    // ordered list
-    else if line.starts_with(char::is_numeric) {
-        match line.split_once('.') {
-            // list item
-            Some((_, rhs)) => Region::OrderedList(vec![rhs.trim_start().to_string()]),
-            // normal text
-            None => Region::Paragraph(vec![line.to_string()]),
+    // math
+    else if let Some(tex) = parse_math_start(line) {
+        match tex.trim() {
+            "" => Region::Math(Vec::new()),
+            tex => handle_in_math_region(tex, elements, Vec::new()),
         }
     }
+    // horizontal rule
+    else if is_rule(line) {
+        elements.push(HTMLElement::Rule);
+        Region::NotSet
+    }
+    // ordered list
+    else if let Some((indent, item)) = parse_ordered_item(line) {
+        Region::OrderedList(new_list(indent, item))
+    }
     // unordered list
-    else if line.starts_with("- ") {
-        Region::UnorderedList(vec![line.trim_start_matches("- ").to_string()])
+    else if let Some((indent, item)) = parse_unordered_item(line) {
+        Region::UnorderedList(new_list(indent, item))
+    }
+    // blockquote
+    else if let Some(quoted) = strip_quote_marker(line) {
+        Region::Blockquote(vec![quoted.to_string()])
+    }
+    // collapsible section
+    else if let Some(summary) = parse_details_start(line) {
+        Region::Details(RawDetails {
+            summary: summary.to_string(),
+            depth: 0,
+            fence: None,
+            lines: Vec::new(),
+        })
+    }
+    // html
+    else if let Some(end) = parse_html_block_start(line, false) {
+        handle_in_html_region(line, elements, Vec::new(), end)
     }
     // table
     else if line.starts_with("|") {
-        let mut headers = Vec::new();
-
-        for cell in line.trim_matches('|').split('|') {
-            headers.push(cell.trim().to_string());
-        }
-
-        Region::Table(headers, Vec::new(), true)
+        Region::TableHeader(line.to_string())
     }
     // paragraph
     else {
@@ -141,162 +418,1262 @@ fn handle_entering_region(line: &String, elements: &mut Vec<HTMLElement>) -> Reg
     }
 }
 
+/// Try to parse a header, which starts with up to six `'#'` characters and then a space.
+/// Optional closing `'#'` characters are removed, as long as they are separated from the content by a space.
+/// Return the level and content of the header.
+fn parse_header(line: &str) -> Option<(usize, String)> {
+    let level = line.find(|c| c != '#').unwrap_or(line.len());
+    if level == 0 || level > MAX_HEADER_LEVEL {
+        return None;
+    }
+
+    // a `#hashtag` is not a header
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    let content = rest.trim();
+    let without_closing = content.trim_end_matches('#');
+    let content = if without_closing.is_empty() || without_closing.ends_with([' ', '\t']) {
+        without_closing.trim_end()
+    } else {
+        content
+    };
+    Some((level, content.to_string()))
+}
+
+/// Try to parse a setext header underline, which is a line of `'='` for level 1 or `'-'` for level 2.
+/// Return the level of the header.
+fn parse_setext_underline(line: &str) -> Option<usize> {
+    if indentation(line) > MAX_REGION_INDENT {
+        return None;
+    }
+
+    let underline = line.trim();
+    match underline.chars().next()? {
+        '=' if underline.chars().all(|c| c == '=') => Some(1),
+        '-' if underline.chars().all(|c| c == '-') => Some(2),
+        _ => None,
+    }
+}
+
 fn handle_in_code_region(
-    line: &String,
+    line: &str,
     elements: &mut Vec<HTMLElement>,
+    fence: Fence,
     lang: String,
     mut lines: Vec<String>,
 ) -> Region {
-    if line.starts_with("```") {
-        elements.push(HTMLElement::Code {
-            language: lang,
-            code: lines,
-        });
+    if is_closing_fence(line, &fence) {
+        close_region(Region::Code(fence, lang, lines), elements);
         Region::NotSet
     } else {
-        lines.push(line.to_string());
-        Region::Code(lang, lines)
+        lines.push(dedent(line, fence.indent));
+        Region::Code(fence, lang, lines)
     }
 }
 
-fn handle_in_ordered_list_region(
-    line: &String,
+fn handle_in_indented_code_region(
+    line: &str,
     elements: &mut Vec<HTMLElement>,
-    mut list: Vec<String>,
+    mut lines: Vec<String>,
 ) -> Region {
-    if line.starts_with(char::is_numeric) {
-        match line.split_once('.') {
-            // list item
-            Some((_, rhs)) => {
-                list.push(rhs.trim_start().to_string());
-                Region::OrderedList(list)
+    if indentation(line) >= CODE_INDENT {
+        lines.push(dedent(line, CODE_INDENT));
+        Region::IndentedCode(lines)
+    } else {
+        close_region(Region::IndentedCode(lines), elements);
+        handle_entering_region(line, elements)
+    }
+}
+
+/// Try to parse the opening fence of a code block, which is at least three backticks or tildes.
+/// The text after a backtick fence cannot contain backticks, so that inline code is not mistaken for a fence.
+/// Return the fence and the text after it.
+fn parse_fence(line: &str) -> Option<(Fence, &str)> {
+    let indent = indentation(line);
+    if indent > MAX_REGION_INDENT {
+        return None;
+    }
+
+    let start = line.trim_start();
+    let marker = start.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let length = start.len() - start.trim_start_matches(marker).len();
+    let info = start[length..].trim();
+    if length < MIN_FENCE_LENGTH || (marker == '`' && info.contains('`')) {
+        return None;
+    }
+
+    let fence = Fence {
+        marker,
+        length,
+        indent,
+    };
+    Some((fence, info))
+}
+
+/// Create a code block from the info string after its opening fence, such as `rust title="main.rs" {3,5-7} showLineNumbers`.
+/// The first word is the language unless it is an attribute, and unknown attributes are ignored.
+/// Code in a diagram language creates a diagram instead, ignoring any attributes.
+fn parse_code_info(info: &str, code: Vec<String>) -> HTMLElement {
+    let mut words = split_info_string(info).into_iter().peekable();
+    let language = words
+        .next_if(|word| !word.starts_with('{') && !word.contains('='))
+        .unwrap_or_default();
+
+    if let Some(kind) = parse_diagram_kind(&language) {
+        return HTMLElement::Diagram {
+            kind,
+            source: code.join("\n"),
+            svg: None,
+        };
+    }
+
+    let mut title = None;
+    let mut highlight = Vec::new();
+    let mut line_numbers = false;
+    for word in words {
+        if let Some(value) = word.strip_prefix("title=") {
+            title = Some(value.trim_matches('"').to_string());
+        } else if let Some(ranges) = word.strip_prefix('{').and_then(|w| w.strip_suffix('}')) {
+            highlight.extend(ranges.split(',').filter_map(parse_line_range));
+        } else if word == "showLineNumbers" {
+            line_numbers = true;
+        } else {
+            warn!("ignoring unknown code block attribute {}", word);
+        }
+    }
+
+    HTMLElement::Code {
+        language,
+        title,
+        highlight,
+        line_numbers,
+        code,
+    }
+}
+
+/// Return the kind of diagram written in the language of a code block, if any.
+fn parse_diagram_kind(language: &str) -> Option<DiagramKind> {
+    match language {
+        "mermaid" => Some(DiagramKind::Mermaid),
+        "dot" => Some(DiagramKind::Dot),
+        _ => None,
+    }
+}
+
+/// Split an info string into words separated by whitespace, keeping quoted text together.
+fn split_info_string(info: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in info.chars() {
+        if c.is_whitespace() && !quoted {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
             }
-            // end of list
-            None => {
-                elements.push(HTMLElement::OrderedList { list });
-                Region::NotSet
+            continue;
+        }
+        if c == '"' {
+            quoted = !quoted;
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Parse a range of highlighted lines, either a single line like `3` or an inclusive range like `5-7`.
+/// Lines are numbered from 1.
+fn parse_line_range(range: &str) -> Option<LineRange> {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    let parsed = start.trim().parse().ok().zip(end.trim().parse().ok());
+    match parsed {
+        Some((start, end)) if 0 < start && start <= end => Some(LineRange { start, end }),
+        _ => {
+            warn!("ignoring invalid highlighted line range {}", range);
+            None
+        }
+    }
+}
+
+/// Whether the line closes the code block, which needs a fence of the same character that is at least as long.
+fn is_closing_fence(line: &str, fence: &Fence) -> bool {
+    let start = line.trim_start();
+    let rest = start.trim_start_matches(fence.marker);
+    indentation(line) <= MAX_REGION_INDENT
+        && start.len() - rest.len() >= fence.length
+        && rest.trim().is_empty()
+}
+
+fn handle_in_math_region(
+    line: &str,
+    elements: &mut Vec<HTMLElement>,
+    mut tex: Vec<String>,
+) -> Region {
+    match line.trim_end().strip_suffix(MATH_MARKER) {
+        Some(last) => {
+            if !last.trim().is_empty() {
+                tex.push(last.to_string());
             }
+            close_region(Region::Math(tex), elements);
+            Region::NotSet
+        }
+        None => {
+            tex.push(line.to_string());
+            Region::Math(tex)
         }
     }
-    // assume that there is a blank line to separate the end of the list
-    else {
-        elements.push(HTMLElement::OrderedList { list });
-        Region::NotSet
+}
+
+/// Try to parse the start of a display math block, which is a line starting with `$$`.
+/// The block can end on the same line if the line also ends with `$$`, and otherwise the line cannot contain another `$$`.
+/// This keeps paragraphs starting with inline display math, like `$$x$$ is large`, as text.
+/// Return the text after the opening `$$`.
+fn parse_math_start(line: &str) -> Option<&str> {
+    if indentation(line) > MAX_REGION_INDENT {
+        return None;
+    }
+
+    let rest = line.trim().strip_prefix(MATH_MARKER)?;
+    (rest.ends_with(MATH_MARKER) || !rest.contains(MATH_MARKER)).then_some(rest)
+}
+
+fn handle_in_ordered_list_region(
+    line: &str,
+    elements: &mut Vec<HTMLElement>,
+    list: RawList,
+) -> Region {
+    match continue_list(line, list, parse_ordered_item) {
+        Ok(list) => Region::OrderedList(list),
+        // the line after a list starts a new region
+        Err(list) => {
+            close_region(Region::OrderedList(list), elements);
+            handle_entering_region(line, elements)
+        }
     }
 }
 
 fn handle_in_unordered_list_region(
-    line: &String,
+    line: &str,
     elements: &mut Vec<HTMLElement>,
-    mut list: Vec<String>,
+    list: RawList,
 ) -> Region {
-    let no_leading_dash = line.trim_start_matches("- ");
-    // end of list
-    if no_leading_dash.len() == line.len() {
-        elements.push(HTMLElement::UnorderedList { list });
-        Region::NotSet
+    match continue_list(line, list, parse_unordered_item) {
+        Ok(list) => Region::UnorderedList(list),
+        // the line after a list starts a new region
+        Err(list) => {
+            close_region(Region::UnorderedList(list), elements);
+            handle_entering_region(line, elements)
+        }
+    }
+}
+
+/// Create a list starting with the given item.
+fn new_list(indent: usize, item: RawListItem) -> RawList {
+    RawList {
+        indent,
+        items: vec![item],
+        after_blank: false,
+        loose: false,
+    }
+}
+
+/// Continue the list with a line that is either nested under the last item or is a new item.
+/// Return the list back as an error if the line is not part of the list.
+fn continue_list(
+    line: &str,
+    mut list: RawList,
+    parse_item: fn(&str) -> Option<(usize, RawListItem)>,
+) -> Result<RawList, RawList> {
+    if push_nested_line(line, list.indent, &mut list.items) {
+        list.after_blank = false;
+        return Ok(list);
+    }
+
+    match parse_item(line) {
+        // a different marker starts a new list
+        Some((_, item)) if item.marker == list.items[0].marker => {
+            list.loose |= list.after_blank;
+            list.after_blank = false;
+            list.items.push(item);
+            Ok(list)
+        }
+        _ => Err(list),
+    }
+}
+
+/// Keep a blank line under the last list item in case the item continues on the next line.
+fn push_blank_line(mut list: RawList) -> RawList {
+    if let Some(item) = list.items.last_mut() {
+        item.nested.push(String::new());
+    }
+    list.after_blank = true;
+    list
+}
+
+/// Try to parse an ordered list item, which starts with a number and then a `'.'` or `')'`.
+/// Return the indentation of the list marker and the item.
+fn parse_ordered_item(line: &str) -> Option<(usize, RawListItem)> {
+    let indent = indentation(line);
+    if indent > MAX_REGION_INDENT {
+        return None;
+    }
+
+    let start = line.trim_start();
+    let digits = start.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 || digits > MAX_ORDERED_DIGITS {
+        return None;
+    }
+
+    let marker = start[digits..]
+        .chars()
+        .next()
+        .filter(|&c| c == '.' || c == ')')?;
+    let mut item = to_list_item(indent, marker, digits + 1, &start[digits + 1..])?;
+    item.number = start[..digits].parse().ok();
+    Some((indent, item))
+}
+
+/// Try to parse an unordered list item, which starts with `'-'`.
+/// The item can be a task starting with a checkbox, either `[ ]` or `[x]`.
+/// Return the indentation of the list marker and the item.
+fn parse_unordered_item(line: &str) -> Option<(usize, RawListItem)> {
+    let indent = indentation(line);
+    // a line like `- - -` is a horizontal rule rather than a list item
+    if indent > MAX_REGION_INDENT || is_rule(line) {
+        return None;
+    }
+
+    let rest = line.trim_start().strip_prefix('-')?;
+    let mut item = to_list_item(indent, '-', 1, rest)?;
+    if let Some((checked, text)) = strip_task_checkbox(&item.text) {
+        item.text = text.to_string();
+        item.checked = Some(checked);
+    }
+    Some((indent, item))
+}
+
+/// Whether the line is a horizontal rule, made of at least three `'-'`, `'*'` or `'_'` characters.
+/// The characters must all be the same, but can be separated by spaces.
+fn is_rule(line: &str) -> bool {
+    if indentation(line) > MAX_REGION_INDENT {
+        return false;
+    }
+
+    let mut chars = line.chars().filter(|c| !c.is_whitespace());
+    let Some(marker) = chars.next() else {
+        return false;
+    };
+    matches!(marker, '-' | '*' | '_')
+        && chars
+            .try_fold(1, |count, c| (c == marker).then_some(count + 1))
+            .is_some_and(|count| count >= 3)
+}
+
+/// Return whether a task's checkbox is ticked and the text after it.
+/// Return `None` if the text does not start with a checkbox.
+fn strip_task_checkbox(text: &str) -> Option<(bool, &str)> {
+    let checked = match text.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+
+    let rest = &text[3..];
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some((checked, rest.trim_start()))
     } else {
-        list.push(no_leading_dash.to_string());
-        Region::UnorderedList(list)
+        None
+    }
+}
+
+/// Create a list item from the text following its marker.
+/// The text must be separated from the marker by at least one space.
+fn to_list_item(
+    indent: usize,
+    marker: char,
+    marker_width: usize,
+    rest: &str,
+) -> Option<RawListItem> {
+    let text = rest.trim_start();
+    let spaces = rest.len() - text.len();
+    if spaces == 0 {
+        return None;
+    }
+
+    Some(RawListItem {
+        marker,
+        number: None,
+        column: indent + marker_width + spaces,
+        text: text.to_string(),
+        checked: None,
+        nested: Vec::new(),
+    })
+}
+
+/// Push the line under the last list item if it is indented further than the list's markers.
+/// Return whether the line was nested.
+fn push_nested_line(line: &str, indent: usize, list: &mut [RawListItem]) -> bool {
+    let line_indent = indentation(line);
+    match list.last_mut() {
+        Some(item) if line_indent >= indent + MIN_NESTED_INDENT => {
+            item.nested.push(dedent(line, item.column));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Return the number of leading spaces of a line, where a tab is worth four spaces.
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// Remove up to `width` spaces of leading indentation from a line.
+fn dedent(line: &str, width: usize) -> String {
+    let mut removed = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let next = if c == '\t' { 4 } else { 1 };
+        if !c.is_whitespace() || removed + next > width {
+            break;
+        }
+        removed += next;
+        chars.next();
+    }
+    chars.collect()
+}
+
+fn handle_in_table_header_region(
+    line: &str,
+    elements: &mut Vec<HTMLElement>,
+    header: String,
+) -> Region {
+    let headers = split_table_row(&header);
+    match parse_separator_row(line, headers.len()) {
+        Some(alignment) => Region::Table(headers, alignment, Vec::new()),
+        // without a matching separator row the header is just a line of text
+        None => handle_in_paragraph_region(line, elements, vec![header.trim().to_string()]),
     }
 }
 
 fn handle_in_table_region(
-    line: &String,
+    line: &str,
     elements: &mut Vec<HTMLElement>,
     headers: Vec<String>,
+    alignment: Vec<Option<Alignment>>,
     mut rows: Vec<Vec<String>>,
-    is_separator: bool,
 ) -> Region {
-    if is_separator {
-        Region::Table(headers, rows, false)
-    } else if line.starts_with("|") {
-        let mut next_row = Vec::new();
-        for cell in line.trim_matches('|').split('|') {
-            next_row.push(cell.trim().to_string());
-        }
-        rows.push(next_row);
-        Region::Table(headers, rows, false)
-    } else {
-        elements.push(HTMLElement::Table { headers, rows });
-        Region::NotSet
+    if line.starts_with("|") {
+        rows.push(split_table_row(line));
+        Region::Table(headers, alignment, rows)
+    }
+    // the line after a table starts a new region
+    else {
+        close_region(Region::Table(headers, alignment, rows), elements);
+        handle_entering_region(line, elements)
     }
 }
 
-fn handle_in_paragraph_region(line: &String, mut lines: Vec<String>) -> Region {
-    // remove trailing "  " for forced line breaks
-    lines.push(line.trim().to_string());
-    Region::Paragraph(lines)
+/// Try to parse the separator row under a table's headers, where every cell looks like `---`, `:---`, `---:` or `:---:`.
+/// The row must have one cell for each of the table's `columns`.
+/// Return the alignment of each column.
+fn parse_separator_row(line: &str, columns: usize) -> Option<Vec<Option<Alignment>>> {
+    let cells = split_table_row(line);
+    let is_separator = |cell: &String| {
+        let dashes = cell.strip_prefix(':').unwrap_or(cell);
+        let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+        !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+    };
+    (cells.len() == columns && cells.iter().all(is_separator))
+        .then(|| cells.iter().map(|cell| parse_alignment(cell)).collect())
 }
 
-fn handle_last_region(region: Region, elements: &mut Vec<HTMLElement>) -> () {
-    match region {
-        Region::NotSet => {}
-        Region::Code(lang, code) => elements.push(HTMLElement::Code {
-            language: lang,
-            code,
-        }),
-        Region::OrderedList(list) => elements.push(HTMLElement::OrderedList { list }),
-        Region::UnorderedList(list) => elements.push(HTMLElement::UnorderedList { list }),
-        Region::Table(headers, rows, _) => elements.push(HTMLElement::Table { headers, rows }),
-        Region::Paragraph(lines) => elements.push(HTMLElement::Paragraph { lines }),
+/// Split a table row into its cells, which have their leading and trailing whitespace trimmed.
+/// The leading and trailing pipes `'|'` of the row are optional.
+/// Pipes escaped by a backslash do not separate cells, and neither do pipes inside code spans.
+fn split_table_row(line: &str) -> Vec<String> {
+    let chars = line.trim().chars().collect::<Vec<char>>();
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut i = usize::from(chars.first() == Some(&'|'));
+    let mut ends_with_pipe = false;
+
+    while i < chars.len() {
+        ends_with_pipe = false;
+        match chars[i] {
+            // the escape is kept so the pipe is unescaped when the cell is parsed
+            '\\' if chars.get(i + 1) == Some(&'|') => {
+                cell.push_str("\\|");
+                i += 2;
+            }
+            '`' => {
+                let end = try_parse_code(&chars, i).map_or(run_end(&chars, i), |(_, end)| end);
+                // code is not unescaped so pipes are unescaped here instead
+                let code = chars[i..end].iter().collect::<String>();
+                cell.push_str(&code.replace("\\|", "|"));
+                i = end;
+            }
+            '|' => {
+                cells.push(cell.trim().to_string());
+                cell.clear();
+                ends_with_pipe = true;
+                i += 1;
+            }
+            c => {
+                cell.push(c);
+                i += 1;
+            }
+        }
     }
+
+    if !ends_with_pipe {
+        cells.push(cell.trim().to_string());
+    }
+    cells
 }
 
-/// Unit tests for the Markdown parser.
-/// These tests cover various Markdown elements such as headers, paragraphs, code blocks, lists,
-/// and tables.
-#[cfg(test)]
-mod tests {
-    use crate::parse::html_element::HTMLElement;
-    use crate::parse::to_html::parse_markdown;
+/// Return the alignment of a column from its cell in the separator row.
+/// A leading `':'` aligns left, a trailing `':'` aligns right and both centre the column.
+fn parse_alignment(cell: &str) -> Option<Alignment> {
+    let cell = cell.trim();
+    match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => Some(Alignment::Center),
+        (true, false) => Some(Alignment::Left),
+        (false, true) => Some(Alignment::Right),
+        (false, false) => None,
+    }
+}
 
-    /// Test for a simple paragraph of the py language.
-    #[test]
-    fn test_code() {
-        let code = vec![
-            "```py".to_string(),
-            "print('hello mate')".to_string(),
-            "print('cya')".to_string(),
-            "```".to_string(),
-        ];
-        assert_eq!(
-            parse_markdown(&code),
-            vec![HTMLElement::Code {
-                language: "py".to_string(),
-                code: vec![
-                    "print('hello mate')".to_string(),
-                    "print('cya')".to_string()
-                ]
-            }]
-        );
+fn handle_in_blockquote_region(
+    line: &str,
+    elements: &mut Vec<HTMLElement>,
+    mut lines: Vec<String>,
+) -> Region {
+    match strip_quote_marker(line) {
+        Some(quoted) => {
+            lines.push(quoted.to_string());
+            Region::Blockquote(lines)
+        }
+        // an unquoted line lazily continues a quoted paragraph
+        None if is_lazy_continuation(line, &lines) => {
+            lines.push(line.to_string());
+            Region::Blockquote(lines)
+        }
+        // the line after a blockquote starts a new region
+        None => {
+            close_region(Region::Blockquote(lines), elements);
+            handle_entering_region(line, elements)
+        }
     }
+}
 
-    /// Test for a simple header and paragraph.
-    #[test]
-    fn test_header_and_text() {
-        let text = vec![
-            "# Overview".to_string(),
-            "".to_string(),
-            "This blog contains some information.  ".to_string(),
-            "The information will be explained below.  ".to_string(),
-            "".to_string(),
-            "## Details".to_string(),
-            "".to_string(),
-            "There are seven countries in the G7.  ".to_string(),
-            "Japan is a part of the G7.  ".to_string(),
-            "".to_string(),
-        ];
-        let parsed = parse_markdown(&text);
-        let exp = vec![
-            HTMLElement::Header {
-                level: 1,
-                content: "Overview".to_string(),
-            },
-            HTMLElement::Paragraph {
+/// Return a quoted line with its leading `'>'` and one optional space removed.
+/// The `'>'` can be indented by up to `MAX_REGION_INDENT` spaces.
+/// Return `None` if the line is not quoted.
+fn strip_quote_marker(line: &str) -> Option<&str> {
+    if indentation(line) > MAX_REGION_INDENT {
+        return None;
+    }
+
+    line.trim_start()
+        .strip_prefix('>')
+        .map(|quoted| quoted.strip_prefix(' ').unwrap_or(quoted))
+}
+
+/// Whether an unquoted line continues the paragraph which the quoted lines end in, rather than ending the blockquote.
+/// Lines that would start another region or turn the paragraph into a header or definition list are not continuations.
+fn is_lazy_continuation(line: &str, quoted: &[String]) -> bool {
+    !interrupts_paragraph(line)
+        && parse_header(line).is_none()
+        && parse_setext_underline(line).is_none()
+        && parse_definition(line).is_none()
+        && quoted.last().is_some_and(|last| !last.trim().is_empty())
+        && ends_in_paragraph(&parse_blocks(quoted).0)
+}
+
+/// Whether the last element is a paragraph, including a paragraph at the end of a nested blockquote or callout.
+fn ends_in_paragraph(elements: &[HTMLElement]) -> bool {
+    match elements.last() {
+        Some(HTMLElement::Paragraph { .. }) => true,
+        Some(HTMLElement::Blockquote { children } | HTMLElement::Callout { children, .. }) => {
+            ends_in_paragraph(children)
+        }
+        _ => false,
+    }
+}
+
+fn handle_in_paragraph_region(
+    line: &str,
+    elements: &mut Vec<HTMLElement>,
+    mut lines: Vec<String>,
+) -> Region {
+    // an underline turns the paragraph into a header, taking precedence over a `---` horizontal rule
+    if let Some(level) = parse_setext_underline(line) {
+        let content = lines.join(" ");
+        elements.push(HTMLElement::Header { level, content });
+        Region::NotSet
+    }
+    // a definition turns the last line of the paragraph into its term
+    else if let Some(definition) = parse_definition(line) {
+        let term = lines.pop().unwrap_or_default();
+        if !lines.is_empty() {
+            close_region(Region::Paragraph(lines), elements);
+        }
+        Region::DefinitionList(RawDefinitionList {
+            items: vec![RawDefinitionItem {
+                term,
+                definitions: vec![vec![definition.to_string()]],
+            }],
+            term: None,
+        })
+    } else if interrupts_paragraph(line) {
+        close_region(Region::Paragraph(lines), elements);
+        handle_entering_region(line, elements)
+    } else {
+        // remove trailing "  " for forced line breaks
+        lines.push(line.trim().to_string());
+        Region::Paragraph(lines)
+    }
+}
+
+fn handle_in_definition_list_region(
+    line: &str,
+    elements: &mut Vec<HTMLElement>,
+    mut list: RawDefinitionList,
+) -> Region {
+    let term = list.term.take();
+    if let Some(definition) = parse_definition(line) {
+        let definition = vec![definition.to_string()];
+        match (term, list.items.last_mut()) {
+            (None, Some(item)) => item.definitions.push(definition),
+            (term, _) => list.items.push(RawDefinitionItem {
+                term: term.unwrap_or_default(),
+                definitions: vec![definition],
+            }),
+        }
+        Region::DefinitionList(list)
+    }
+    // the term was not followed by a definition so it starts a paragraph instead
+    else if let Some(term) = term {
+        close_region(Region::DefinitionList(list), elements);
+        handle_in_paragraph_region(line, elements, vec![term])
+    }
+    // indented lines continue the last definition
+    else if indentation(line) >= DEFINITION_INDENT {
+        if let Some(definition) = last_definition(&mut list) {
+            definition.push(dedent(line, DEFINITION_INDENT));
+        }
+        Region::DefinitionList(list)
+    }
+    // the line after a definition list starts a new region
+    else if interrupts_paragraph(line) || parse_header(line).is_some() || line.starts_with('|') {
+        close_region(Region::DefinitionList(list), elements);
+        handle_entering_region(line, elements)
+    } else {
+        list.term = Some(line.trim().to_string());
+        Region::DefinitionList(list)
+    }
+}
+
+/// Return the lines of the last definition in the list.
+fn last_definition(list: &mut RawDefinitionList) -> Option<&mut Vec<String>> {
+    list.items.last_mut()?.definitions.last_mut()
+}
+
+/// Try to parse a definition, which starts with `':'` and then a space.
+/// Return the text of the definition.
+fn parse_definition(line: &str) -> Option<&str> {
+    if indentation(line) > MAX_REGION_INDENT {
+        return None;
+    }
+
+    let rest = line.trim_start().strip_prefix(':')?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+}
+
+fn handle_in_details_region(
+    line: &str,
+    elements: &mut Vec<HTMLElement>,
+    mut details: RawDetails,
+) -> Region {
+    // fenced code can contain anything, including the closing marker
+    if let Some(fence) = &details.fence {
+        if is_closing_fence(line, fence) {
+            details.fence = None;
+        }
+    } else if let Some((fence, _)) = parse_fence(line) {
+        details.fence = Some(fence);
+    } else if parse_details_start(line).is_some() {
+        details.depth += 1;
+    } else if is_details_end(line) {
+        if details.depth == 0 {
+            close_region(Region::Details(details), elements);
+            return Region::NotSet;
+        }
+        details.depth -= 1;
+    }
+
+    details.lines.push(line.to_string());
+    Region::Details(details)
+}
+
+/// Try to parse the start of a collapsible section, which looks like `:::details Summary`.
+/// Return the summary, which can be empty.
+fn parse_details_start(line: &str) -> Option<&str> {
+    if indentation(line) > MAX_REGION_INDENT {
+        return None;
+    }
+
+    let rest = line.trim().strip_prefix(DETAILS_MARKER)?.trim_start();
+    let summary = rest.strip_prefix("details")?;
+    (summary.is_empty() || summary.starts_with(char::is_whitespace)).then(|| summary.trim())
+}
+
+/// Whether the line closes a collapsible section.
+fn is_details_end(line: &str) -> bool {
+    indentation(line) <= MAX_REGION_INDENT && line.trim() == DETAILS_MARKER
+}
+
+fn handle_in_html_region(
+    line: &str,
+    elements: &mut Vec<HTMLElement>,
+    mut lines: Vec<String>,
+    end: Option<String>,
+) -> Region {
+    lines.push(line.to_string());
+    let ended = end
+        .as_ref()
+        .is_some_and(|end| line.to_lowercase().contains(end.as_str()));
+    if ended {
+        close_region(Region::Html(lines, end), elements);
+        Region::NotSet
+    } else {
+        Region::Html(lines, end)
+    }
+}
+
+/// Try to parse the start of a HTML block, which is a comment or a tag at the start of the line.
+/// Comments and tags like `<pre>` end at the text closing them, while other blocks end at a blank line.
+/// Tags that are not block tags, like `<kbd>`, only start a block when they are alone on their line and not directly after a paragraph.
+/// Return the text ending the block, or `None` if the block ends at a blank line.
+fn parse_html_block_start(line: &str, in_paragraph: bool) -> Option<Option<String>> {
+    if indentation(line) > MAX_REGION_INDENT {
+        return None;
+    }
+
+    if line.trim_start().starts_with("<!--") {
+        return Some(Some("-->".to_string()));
+    }
+
+    let chars = line.trim().chars().collect::<Vec<char>>();
+    let (tag, end) = parse_tag(&chars, 0)?;
+    if HTML_RAW_TAGS.contains(&tag.name.as_str()) && !tag.closing {
+        Some(Some(format!("</{}>", tag.name)))
+    } else if HTML_BLOCK_TAGS.contains(&tag.name.as_str()) || (!in_paragraph && end == chars.len())
+    {
+        Some(None)
+    } else {
+        None
+    }
+}
+
+/// Whether the line starts a region that can directly follow a paragraph without a blank line.
+/// This lets list items hold a paragraph directly followed by a child list or code block.
+/// Only ordered lists starting from 1 can follow a paragraph, so wrapped text starting with a number stays in the paragraph.
+fn interrupts_paragraph(line: &str) -> bool {
+    parse_fence(line).is_some()
+        || parse_math_start(line).is_some()
+        || parse_details_start(line).is_some()
+        || parse_html_block_start(line, true).is_some()
+        || is_rule(line)
+        || strip_quote_marker(line).is_some()
+        || parse_ordered_item(line).is_some_and(|(_, item)| item.number == Some(1))
+        || parse_unordered_item(line).is_some()
+}
+
+/// Convert a finished region into its HTML element.
+/// Text which can contain inline Markdown is parsed at this point.
+fn close_region(region: Region, elements: &mut Vec<HTMLElement>) {
+    match region {
+        Region::NotSet => {}
+        // the section's lines can hold any other region so are parsed recursively
+        Region::Details(details) => elements.push(HTMLElement::Details {
+            summary: parse_unresolved_inline(&details.summary),
+            children: parse_blocks(&details.lines).0,
+        }),
+        // each definition can hold any other region so is parsed recursively
+        Region::DefinitionList(list) => {
+            let items = list
+                .items
+                .into_iter()
+                .map(|item| DefinitionItem {
+                    term: parse_unresolved_inline(&item.term),
+                    definitions: item
+                        .definitions
+                        .iter()
+                        .map(|definition| parse_blocks(definition).0)
+                        .collect(),
+                })
+                .collect();
+            elements.push(HTMLElement::DefinitionList { items });
+            // a term without a definition is a paragraph
+            if let Some(term) = list.term {
+                close_region(Region::Paragraph(vec![term]), elements);
+            }
+        }
+        Region::Html(lines, _) => elements.push(HTMLElement::HtmlBlock {
+            html: lines.join("\n"),
+        }),
+        Region::Code(_, info, code) => elements.push(parse_code_info(&info, code)),
+        // blank lines are kept inside the math but not at its end
+        Region::Math(mut tex) => {
+            while tex.last().is_some_and(|line| line.trim().is_empty()) {
+                tex.pop();
+            }
+            elements.push(HTMLElement::Math {
+                display: true,
+                tex: tex.join("\n"),
+            })
+        }
+        // blank lines are kept inside the code but not at its end
+        Region::IndentedCode(mut code) => {
+            while code.last().is_some_and(|line| line.trim().is_empty()) {
+                code.pop();
+            }
+            elements.push(parse_code_info("", code))
+        }
+        Region::OrderedList(list) => {
+            let start = list.items[0].number.unwrap_or(1);
+            let (list, tight) = parse_list_items(list);
+            elements.push(HTMLElement::OrderedList { start, list, tight })
+        }
+        Region::UnorderedList(list) => {
+            let (list, tight) = parse_list_items(list);
+            elements.push(HTMLElement::UnorderedList { list, tight })
+        }
+        // a header without a separator row is a paragraph
+        Region::TableHeader(header) => {
+            close_region(Region::Paragraph(vec![header.trim().to_string()]), elements)
+        }
+        Region::Table(headers, mut alignment, mut rows) => {
+            // every row is made as wide as the headers
+            let width = headers.len();
+            alignment.resize(width, None);
+            for row in rows.iter_mut() {
+                if row.len() != width {
+                    warn!(
+                        "table row has {} cells but there are {} headers: {:?}",
+                        row.len(),
+                        width,
+                        row
+                    );
+                }
+                row.resize(width, String::new());
+            }
+
+            elements.push(HTMLElement::Table {
+                headers: parse_lines(&headers),
+                alignment,
+                rows: rows.iter().map(|row| parse_lines(row)).collect(),
+            })
+        }
+        Region::Paragraph(lines) => {
+            let mut lines = parse_lines(&lines);
+            // a paragraph holding only an image is shown as a standalone image
+            match lines.as_mut_slice() {
+                [line] if matches!(line.as_slice(), [Inline::Image(_)]) => {
+                    if let Some(Inline::Image(image)) = line.pop() {
+                        elements.push(HTMLElement::Image(image));
+                    }
+                }
+                _ => elements.push(HTMLElement::Paragraph { lines }),
+            }
+        }
+        // quoted lines can hold any other region so are parsed recursively
+        Region::Blockquote(lines) => {
+            match lines.first().and_then(|first| parse_callout_kind(first)) {
+                Some(kind) => elements.push(HTMLElement::Callout {
+                    kind,
+                    children: parse_blocks(&lines[1..]).0,
+                }),
+                None => elements.push(HTMLElement::Blockquote {
+                    children: parse_blocks(&lines).0,
+                }),
+            }
+        }
+    }
+}
+
+/// Return the kind of callout if the line is a callout marker, e.g. `[!NOTE]`.
+/// The kind is case-insensitive.
+fn parse_callout_kind(line: &str) -> Option<CalloutKind> {
+    let kind = line.trim().strip_prefix("[!")?.strip_suffix(']')?;
+    match kind.to_uppercase().as_str() {
+        "NOTE" => Some(CalloutKind::Note),
+        "TIP" => Some(CalloutKind::Tip),
+        "IMPORTANT" => Some(CalloutKind::Important),
+        "WARNING" => Some(CalloutKind::Warning),
+        "CAUTION" => Some(CalloutKind::Caution),
+        _ => None,
+    }
+}
+
+/// Parse each list item into the lines of its leading paragraph and the elements which follow.
+/// An item's lines can hold any other region, such as child lists, so are parsed recursively.
+/// Also return whether the list is tight, i.e. no blank lines separate its items or the elements inside them.
+fn parse_list_items(list: RawList) -> (Vec<ListItem>, bool) {
+    let mut tight = !list.loose;
+    let mut items = Vec::new();
+
+    for item in list.items {
+        let checked = item.checked;
+        let mut text = vec![item.text];
+        text.extend(item.nested);
+
+        let (mut children, separated) = parse_blocks(&text);
+        tight &= !separated;
+
+        let lines = match children.first_mut() {
+            Some(HTMLElement::Paragraph { lines }) => {
+                let lines = std::mem::take(lines);
+                children.remove(0);
+                lines
+            }
+            _ => Vec::new(),
+        };
+        items.push(ListItem {
+            checked,
+            lines,
+            children,
+        });
+    }
+
+    (items, tight)
+}
+
+/// Parse the inline elements of each line.
+fn parse_lines(lines: &[String]) -> Vec<Vec<Inline>> {
+    lines
+        .iter()
+        .map(|line| parse_unresolved_inline(line))
+        .collect()
+}
+
+/// Remove footnote definitions like `[^label]: text` and link definitions like `[reference]: href "title"` from the Markdown text, skipping over fenced code.
+/// Lines after a footnote definition that are indented by four spaces continue the footnote, even after blank lines.
+/// Return the remaining text, the parsed footnotes and the link definitions keyed by their normalised reference.
+fn extract_definitions(
+    text: &[String],
+) -> (
+    Vec<String>,
+    Vec<RawFootnote>,
+    HashMap<String, LinkDefinition>,
+) {
+    let mut definitions = Vec::new();
+    let mut links = HashMap::new();
+    let kept = find_definitions(text, &mut definitions, &mut links);
+    let remaining = text
+        .iter()
+        .zip(kept)
+        .filter(|(_, keep)| *keep)
+        .map(|(line, _)| line.clone())
+        .collect();
+    (remaining, definitions, links)
+}
+
+/// Parse the footnote and link definitions in the text, adding them to those already found.
+/// Definitions must start a block, so a definition-like line continuing a paragraph is kept as text.
+/// Definitions inside blockquotes are found with their quote markers removed.
+/// Return whether each line should be kept, i.e. it is not part of a definition.
+fn find_definitions(
+    text: &[String],
+    definitions: &mut Vec<RawFootnote>,
+    links: &mut HashMap<String, LinkDefinition>,
+) -> Vec<bool> {
+    let mut kept = Vec::new();
+    let mut fence: Option<Fence> = None;
+    let mut in_paragraph = false;
+
+    let mut i = 0;
+    while i < text.len() {
+        let line = &text[i];
+        i += 1;
+
+        if let Some(open) = &fence {
+            if is_closing_fence(line, open) {
+                fence = None;
+            }
+        } else if let Some((open, _)) = parse_fence(line) {
+            fence = Some(open);
+        } else if strip_quote_marker(line).is_some() {
+            // the quoted lines are searched on their own, as a quote holds its own blocks
+            let mut quoted = vec![];
+            for next in &text[i - 1..] {
+                match strip_quote_marker(next) {
+                    Some(inner) => quoted.push(inner.to_string()),
+                    None => break,
+                }
+            }
+            i += quoted.len() - 1;
+            kept.extend(find_definitions(&quoted, definitions, links));
+            in_paragraph = quoted.last().is_some_and(|last| !last.trim().is_empty());
+            continue;
+        } else if let Some((label, rest)) =
+            parse_footnote_definition(line).filter(|_| !in_paragraph)
+        {
+            let mut lines = vec![rest.to_string()];
+            kept.push(false);
+            while let Some(next) = text
+                .get(i)
+                .filter(|next| continues_footnote(next, &text[i..]))
+            {
+                lines.push(dedent(next, CODE_INDENT));
+                kept.push(false);
+                i += 1;
+            }
+
+            if definitions
+                .iter()
+                .any(|definition| definition.label == label)
+            {
+                warn!("ignoring duplicate definition of footnote {}", label);
+            } else {
+                let children = parse_blocks(&lines).0;
+                definitions.push(RawFootnote { label, children });
+            }
+            continue;
+        } else if let Some((reference, definition)) =
+            parse_link_reference_definition(line).filter(|_| !in_paragraph)
+        {
+            match links.entry(reference) {
+                Entry::Occupied(entry) => {
+                    warn!("ignoring duplicate definition of link {}", entry.key())
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(definition);
+                }
+            }
+            kept.push(false);
+            continue;
+        }
+
+        // only text can continue a paragraph, which ends at a blank line or a block that cannot hold text after it
+        in_paragraph = fence.is_none()
+            && parse_fence(line).is_none()
+            && !line.trim().is_empty()
+            && (in_paragraph || indentation(line) <= MAX_REGION_INDENT)
+            && parse_header(line).is_none()
+            && !is_rule(line);
+        kept.push(true);
+    }
+    kept
+}
+
+/// Try to parse a link definition, which looks like `[reference]: href "title"` where the title is optional.
+/// Return the normalised reference and the definition.
+fn parse_link_reference_definition(line: &str) -> Option<(String, LinkDefinition)> {
+    if indentation(line) > MAX_REGION_INDENT {
+        return None;
+    }
+
+    let rest = line.trim_start().strip_prefix('[')?;
+    let (reference, text) = rest.split_once("]:")?;
+    if reference.trim().is_empty() || reference.starts_with('^') || reference.contains(['[', ']']) {
+        return None;
+    }
+    Some((normalise_reference(reference), parse_link_definition(text)?))
+}
+
+/// Try to parse the start of a footnote definition, which looks like `[^label]: text`.
+/// Return the label and the text after the colon.
+fn parse_footnote_definition(line: &str) -> Option<(String, &str)> {
+    if indentation(line) > MAX_REGION_INDENT {
+        return None;
+    }
+
+    let rest = line.trim_start().strip_prefix("[^")?;
+    let (label, text) = rest.split_once("]:")?;
+    if label.is_empty() || label.contains(|c: char| c.is_whitespace() || c == '[' || c == ']') {
+        return None;
+    }
+    Some((label.to_string(), text.trim()))
+}
+
+/// Whether the next line continues a footnote definition.
+/// It must be indented, or be blank with the next non-blank line indented.
+fn continues_footnote(next: &str, rest: &[String]) -> bool {
+    if !next.trim().is_empty() {
+        return indentation(next) >= CODE_INDENT;
+    }
+    rest.iter()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| indentation(line) >= CODE_INDENT)
+}
+
+/// Number the footnote references in order of first use and add the referenced footnotes to the end of the elements.
+/// References inside footnotes can refer to further footnotes, which are numbered after all references in the main text.
+/// References to undefined footnotes are kept as plain text.
+fn number_footnotes(elements: &mut Vec<HTMLElement>, mut definitions: Vec<RawFootnote>) {
+    let mut notes: Vec<Footnote> = Vec::new();
+    for_each_inline(elements, &mut |inline| {
+        number_reference(inline, &mut notes, &mut definitions)
+    });
+
+    let mut i = 0;
+    while i < notes.len() {
+        let mut children = std::mem::take(&mut notes[i].children);
+        for_each_inline(&mut children, &mut |inline| {
+            number_reference(inline, &mut notes, &mut definitions)
+        });
+        notes[i].children = children;
+        i += 1;
+    }
+
+    for definition in definitions {
+        warn!("footnote {} is never referenced", definition.label);
+    }
+    if !notes.is_empty() {
+        elements.push(HTMLElement::Footnotes { notes });
+    }
+}
+
+/// Set the number of a footnote reference, starting a new footnote if it is the first reference to its definition.
+fn number_reference(
+    inline: &mut Inline,
+    notes: &mut Vec<Footnote>,
+    definitions: &mut Vec<RawFootnote>,
+) {
+    let Inline::FootnoteReference {
+        label,
+        number,
+        occurrence,
+    } = inline
+    else {
+        return;
+    };
+
+    if let Some(note) = notes.iter_mut().find(|note| note.label == *label) {
+        note.references += 1;
+        *number = note.number;
+        *occurrence = note.references;
+    } else if let Some(position) = definitions
+        .iter()
+        .position(|definition| definition.label == *label)
+    {
+        let definition = definitions.remove(position);
+        *number = notes.len() + 1;
+        *occurrence = 1;
+        notes.push(Footnote {
+            number: *number,
+            label: definition.label,
+            references: 1,
+            children: definition.children,
+        });
+    } else {
+        warn!("footnote {} is not defined", label);
+        let text = format!("[^{}]", label);
+        *inline = Inline::Text { text };
+    }
+}
+
+/// Unit tests for the Markdown parser.
+/// These tests cover various Markdown elements such as headers, paragraphs, code blocks, lists,
+/// tables and blockquotes.
+#[cfg(test)]
+mod tests {
+    use crate::parse::html::HtmlSanitizer;
+    use crate::parse::html_element::{
+        Alignment, CalloutKind, DefinitionItem, DiagramKind, Footnote, HTMLElement, LineRange,
+        ListItem,
+    };
+    use crate::parse::inline::{Image, Inline};
+    use crate::parse::to_html::{parse_markdown, parse_markdown_with_sanitizer};
+
+    /// Shorthand for an inline element of plain text.
+    fn text_node(text: &str) -> Inline {
+        Inline::Text {
+            text: text.to_string(),
+        }
+    }
+
+    /// Shorthand for an inline code span.
+    fn code(code: &str) -> Inline {
+        Inline::Code {
+            code: code.to_string(),
+        }
+    }
+
+    /// Shorthand for a line holding only plain text.
+    fn plain(text: &str) -> Vec<Inline> {
+        vec![text_node(text)]
+    }
+
+    /// Shorthand for the expected list item with a single line and without any nested elements.
+    fn item(line: Vec<Inline>) -> ListItem {
+        ListItem {
+            checked: None,
+            lines: vec![line],
+            children: vec![],
+        }
+    }
+
+    /// Test for a simple paragraph of the py language.
+    #[test]
+    fn test_code() {
+        let code = vec![
+            "```py".to_string(),
+            "print('hello mate')".to_string(),
+            "print('cya')".to_string(),
+            "```".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&code),
+            vec![HTMLElement::Code {
+                language: "py".to_string(),
+                title: None,
+                highlight: vec![],
+                line_numbers: false,
+                code: vec![
+                    "print('hello mate')".to_string(),
+                    "print('cya')".to_string()
+                ]
+            }]
+        );
+    }
+
+    /// Test for a simple header and paragraph.
+    #[test]
+    fn test_header_and_text() {
+        let text = vec![
+            "# Overview".to_string(),
+            "".to_string(),
+            "This blog contains some information.  ".to_string(),
+            "The information will be explained below.  ".to_string(),
+            "".to_string(),
+            "## Details".to_string(),
+            "".to_string(),
+            "There are seven countries in the G7.  ".to_string(),
+            "Japan is a part of the G7.  ".to_string(),
+            "".to_string(),
+        ];
+        let parsed = parse_markdown(&text);
+        let exp = vec![
+            HTMLElement::Header {
+                level: 1,
+                content: "Overview".to_string(),
+            },
+            HTMLElement::Paragraph {
                 lines: vec![
-                    "This blog contains some information.".to_string(),
-                    "The information will be explained below.".to_string(),
+                    plain("This blog contains some information."),
+                    plain("The information will be explained below."),
                 ],
             },
             HTMLElement::Header {
@@ -305,8 +1682,8 @@ mod tests {
             },
             HTMLElement::Paragraph {
                 lines: vec![
-                    "There are seven countries in the G7.".to_string(),
-                    "Japan is a part of the G7.".to_string(),
+                    plain("There are seven countries in the G7."),
+                    plain("Japan is a part of the G7."),
                 ],
             },
         ];
@@ -326,19 +1703,20 @@ mod tests {
             "| p      | go to previous window |".to_string(),
             "| n      | go to next window     |".to_string(),
         ];
-        let exp_headers = vec!["Letter".to_string(), "Description".to_string()];
+        let exp_headers = vec![plain("Letter"), plain("Description")];
         let exp_rows = vec![
-            vec!["c".to_string(), "make new window".to_string()],
-            vec!["&".to_string(), "kill current window".to_string()],
-            vec!["1..9".to_string(), "go to window 1..9".to_string()],
-            vec![",".to_string(), "rename window".to_string()],
-            vec!["p".to_string(), "go to previous window".to_string()],
-            vec!["n".to_string(), "go to next window".to_string()],
+            vec![plain("c"), plain("make new window")],
+            vec![plain("&"), plain("kill current window")],
+            vec![plain("1..9"), plain("go to window 1..9")],
+            vec![plain(","), plain("rename window")],
+            vec![plain("p"), plain("go to previous window")],
+            vec![plain("n"), plain("go to next window")],
         ];
         assert_eq!(
             parse_markdown(&table),
             vec![HTMLElement::Table {
                 headers: exp_headers,
+                alignment: vec![None, None],
                 rows: exp_rows
             }]
         );
@@ -385,16 +1763,23 @@ mod tests {
             content: "Overview".to_string(),
         };
         let para1= HTMLElement::Paragraph { lines: vec![
-            "The `upper_bound` and `lower_bound` functions give iterators to the first element matching a condition.".to_string(),
-            "It is possible to change the behaviour so that the last position is instead returned.".to_string(),
+            vec![
+                text_node("The "),
+                Inline::Code { code: "upper_bound".to_string() },
+                text_node(" and "),
+                Inline::Code { code: "lower_bound".to_string() },
+                text_node(" functions give iterators to the first element matching a condition."),
+            ],
+            plain("It is possible to change the behaviour so that the last position is instead returned."),
         ] };
         let para2 = HTMLElement::Paragraph {
-            lines: vec![
-                "Use reverse iterators in conjunction with the `greater<N>` comparator to do this."
-                    .to_string(),
-            ],
+            lines: vec![vec![
+                text_node("Use reverse iterators in conjunction with the "),
+                code("greater<N>"),
+                text_node(" comparator to do this."),
+            ]],
         };
-        let code = HTMLElement::Code { language: "cpp".to_string(), code: vec![
+        let code = HTMLElement::Code { language: "cpp".to_string(), title: None, highlight: vec![], line_numbers: false, code: vec![
             "#include <algorithm>".to_string(),
             "#include <cassert>".to_string(),
             "#include <utility>".to_string(),
@@ -465,39 +1850,1518 @@ mod tests {
 
         assert_eq!(parse_markdown(&blog), vec![
             HTMLElement::Header { level: 1, content: "Notes".to_string() },
-            HTMLElement::OrderedList { list: vec![
-                "Enter `tmux` to start".to_string(),
-                "Cannot enter `Command + k` to clear screen".to_string(),
-                "Any command letter that is a shift-pressed key, must have shift pressed to work".to_string(),
-            ] },
+            HTMLElement::OrderedList { start: 1, list: vec![
+                item(vec![text_node("Enter "), code("tmux"), text_node(" to start")]),
+                item(vec![text_node("Cannot enter "), code("Command + k"), text_node(" to clear screen")]),
+                item(plain("Any command letter that is a shift-pressed key, must have shift pressed to work")),
+            ], tight: true },
             HTMLElement::Header { level: 1, content: "Modifier".to_string() },
             HTMLElement::Paragraph { lines: vec![
-                "Press the modifier key and then a command letter.".to_string(),
-                "In Zac's `.tmux.conf` this was `Control + a`.".to_string(),
+                plain("Press the modifier key and then a command letter."),
+                vec![text_node("In Zac's "), code(".tmux.conf"), text_node(" this was "), code("Control + a"), text_node(".")],
             ] },
-            HTMLElement::UnorderedList { list: vec!["It is apparently the most ergonomic combination.".to_string()] },
+            HTMLElement::UnorderedList { list: vec![item(plain("It is apparently the most ergonomic combination."))], tight: true },
             HTMLElement::Paragraph { lines: vec![
-                "By default it is `Control + b`.".to_string(),
-                "You have to release the modifier and then press the command letter as per this [guide](https://superuser.com/questions/266725/tmux-ctrlb-not-working).".to_string(),
-                "This is a list of [default command letters](https://man.openbsd.org/tmux#DEFAULT_KEY_BINDINGS).".to_string(),
+                vec![text_node("By default it is "), code("Control + b"), text_node(".")],
+                vec![
+                    text_node("You have to release the modifier and then press the command letter as per this "),
+                    Inline::Link {
+                        href: "https://superuser.com/questions/266725/tmux-ctrlb-not-working".to_string(),
+                        title: None,
+                        label: plain("guide"),
+                    },
+                    text_node("."),
+                ],
+                vec![
+                    text_node("This is a list of "),
+                    Inline::Link {
+                        href: "https://man.openbsd.org/tmux#DEFAULT_KEY_BINDINGS".to_string(),
+                        title: None,
+                        label: plain("default command letters"),
+                    },
+                    text_node("."),
+                ],
             ] },
             HTMLElement::Header { level: 1, content: "Windows".to_string() },
-            HTMLElement::Paragraph { lines: vec!["They are more like tabs in a browser.".to_string() ] },
-            HTMLElement::Table { headers: vec!["Letter".to_string(), "Description".to_string()], rows: vec![
-                vec!["c".to_string(), "make new window".to_string()],
-                vec!["&".to_string(), "kill current window".to_string()],
-                vec!["1..9".to_string(), "go to window 1..9".to_string()],
-                vec![",".to_string(), "rename window".to_string()],
-                vec!["p".to_string(), "go to previous window".to_string()],
-                vec!["n".to_string(), "go to next window".to_string()],
-                vec!["x".to_string(), "close window".to_string()],
+            HTMLElement::Paragraph { lines: vec![plain("They are more like tabs in a browser.") ] },
+            HTMLElement::Table { headers: vec![plain("Letter"), plain("Description")], alignment: vec![None, None], rows: vec![
+                vec![plain("c"), plain("make new window")],
+                vec![plain("&"), plain("kill current window")],
+                vec![plain("1..9"), plain("go to window 1..9")],
+                vec![plain(","), plain("rename window")],
+                vec![plain("p"), plain("go to previous window")],
+                vec![plain("n"), plain("go to next window")],
+                vec![plain("x"), plain("close window")],
             ] },
             HTMLElement::Header { level: 1, content: "Pane".to_string() },
             HTMLElement::Paragraph { lines: vec![
-                "A window can be split into panes.".to_string(),
-                "Panes are closed by `Control + d` or the command letter `x`.".to_string(),
-                "Can switch using arrow keys as the command letter or `o`.".to_string(),
+                plain("A window can be split into panes."),
+                vec![text_node("Panes are closed by "), code("Control + d"), text_node(" or the command letter "), code("x"), text_node(".")],
+                vec![text_node("Can switch using arrow keys as the command letter or "), code("o"), text_node(".")],
             ] }
         ]);
     }
+
+    /// Test that list items and table cells are parsed into inline elements.
+    #[test]
+    fn test_inline_in_list_and_table() {
+        let text = vec![
+            "- run **`ls`** first".to_string(),
+            "".to_string(),
+            "| Command | Source |".to_string(),
+            "| ------- | ------ |".to_string(),
+            "| `pwd`   | [man](https://man7.org) |".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::UnorderedList {
+                    list: vec![ListItem {
+                        checked: None,
+                        lines: vec![vec![
+                            text_node("run "),
+                            Inline::Strong {
+                                children: vec![Inline::Code {
+                                    code: "ls".to_string()
+                                }]
+                            },
+                            text_node(" first"),
+                        ]],
+                        children: vec![],
+                    }],
+                    tight: true
+                },
+                HTMLElement::Table {
+                    headers: vec![plain("Command"), plain("Source")],
+                    alignment: vec![None, None],
+                    rows: vec![vec![
+                        vec![Inline::Code {
+                            code: "pwd".to_string()
+                        }],
+                        vec![Inline::Link {
+                            href: "https://man7.org".to_string(),
+                            title: None,
+                            label: plain("man"),
+                        }],
+                    ]],
+                },
+            ]
+        );
+    }
+
+    /// Test that a blockquote holds nested block elements.
+    #[test]
+    fn test_blockquote() {
+        let text = vec![
+            "> The `find` utility recursively descends the directory tree.".to_string(),
+            ">".to_string(),
+            "> - `-exec` runs a command".to_string(),
+            "> - `-print` prints the path".to_string(),
+            ">".to_string(),
+            "> ```sh".to_string(),
+            ">     find . -name '*.md'".to_string(),
+            "> ```".to_string(),
+            "".to_string(),
+            "Taken from the man page.".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Blockquote {
+                    children: vec![
+                        HTMLElement::Paragraph {
+                            lines: vec![vec![
+                                text_node("The "),
+                                code("find"),
+                                text_node(" utility recursively descends the directory tree.")
+                            ]]
+                        },
+                        HTMLElement::UnorderedList {
+                            list: vec![
+                                item(vec![code("-exec"), text_node(" runs a command")]),
+                                item(vec![code("-print"), text_node(" prints the path")])
+                            ],
+                            tight: true
+                        },
+                        HTMLElement::Code {
+                            language: "sh".to_string(),
+                            title: None,
+                            highlight: vec![],
+                            line_numbers: false,
+                            code: vec!["    find . -name '*.md'".to_string()]
+                        },
+                    ]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("Taken from the man page.")]
+                },
+            ]
+        );
+    }
+
+    /// Test that blockquotes can be nested and end at the first unquoted line.
+    #[test]
+    fn test_nested_blockquote() {
+        let text = vec![
+            "> outer".to_string(),
+            ">".to_string(),
+            "> > inner".to_string(),
+            "# After".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Blockquote {
+                    children: vec![
+                        HTMLElement::Paragraph {
+                            lines: vec![plain("outer")]
+                        },
+                        HTMLElement::Blockquote {
+                            children: vec![HTMLElement::Paragraph {
+                                lines: vec![plain("inner")]
+                            }]
+                        },
+                    ]
+                },
+                HTMLElement::Header {
+                    level: 1,
+                    content: "After".to_string()
+                },
+            ]
+        );
+    }
+
+    /// Test that the quote marker can be indented by up to three spaces.
+    #[test]
+    fn test_indented_blockquote() {
+        let text = vec![
+            "   > indented".to_string(),
+            "  > quote".to_string(),
+            "".to_string(),
+            "    > code".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Blockquote {
+                    children: vec![HTMLElement::Paragraph {
+                        lines: vec![plain("indented"), plain("quote")]
+                    }]
+                },
+                HTMLElement::Code {
+                    language: "".to_string(),
+                    title: None,
+                    highlight: vec![],
+                    line_numbers: false,
+                    code: vec!["> code".to_string()]
+                },
+            ]
+        );
+    }
+
+    /// Test that unquoted lines after a quoted paragraph lazily continue it, unless they start another region.
+    #[test]
+    fn test_blockquote_lazy_continuation() {
+        let text = vec![
+            "> > nested".to_string(),
+            "lazy".to_string(),
+            "continuation".to_string(),
+            "- item".to_string(),
+            "".to_string(),
+            "> ```".to_string(),
+            "> code".to_string(),
+            "not code".to_string(),
+        ];
+        let paragraph = |lines: &[&str]| HTMLElement::Paragraph {
+            lines: lines.iter().map(|text| plain(text)).collect(),
+        };
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Blockquote {
+                    children: vec![HTMLElement::Blockquote {
+                        children: vec![paragraph(&["nested", "lazy", "continuation"])]
+                    }]
+                },
+                HTMLElement::UnorderedList {
+                    list: vec![ListItem {
+                        checked: None,
+                        lines: vec![plain("item")],
+                        children: vec![],
+                    }],
+                    tight: true
+                },
+                HTMLElement::Blockquote {
+                    children: vec![HTMLElement::Code {
+                        language: "".to_string(),
+                        title: None,
+                        highlight: vec![],
+                        line_numbers: false,
+                        code: vec!["code".to_string()]
+                    }]
+                },
+                paragraph(&["not code"]),
+            ]
+        );
+    }
+
+    /// Test that a blockquote starting with a callout marker becomes a callout.
+    #[test]
+    fn test_callout() {
+        let text = vec![
+            "> [!WARNING]".to_string(),
+            "> Do not store `$?` in a variable.".to_string(),
+            "".to_string(),
+            "> [!tip]".to_string(),
+            "> Kinds are case-insensitive.".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Callout {
+                    kind: CalloutKind::Warning,
+                    children: vec![HTMLElement::Paragraph {
+                        lines: vec![vec![
+                            text_node("Do not store "),
+                            code("$?"),
+                            text_node(" in a variable.")
+                        ]]
+                    }]
+                },
+                HTMLElement::Callout {
+                    kind: CalloutKind::Tip,
+                    children: vec![HTMLElement::Paragraph {
+                        lines: vec![plain("Kinds are case-insensitive.")]
+                    }]
+                },
+            ]
+        );
+    }
+
+    /// Test that an unknown callout marker is left as a normal blockquote.
+    #[test]
+    fn test_unknown_callout() {
+        let text = vec!["> [!RANDOM]".to_string(), "> text".to_string()];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![HTMLElement::Blockquote {
+                children: vec![HTMLElement::Paragraph {
+                    lines: vec![plain("[!RANDOM]"), plain("text")]
+                }]
+            }]
+        );
+    }
+
+    /// Test that indented list items are nested under the previous item.
+    #[test]
+    fn test_nested_lists() {
+        let text = vec![
+            "1. Install".to_string(),
+            "   - `brew install tmux`".to_string(),
+            "     1. check `tmux -V`".to_string(),
+            "   - `apt install tmux`".to_string(),
+            "2. Configure".to_string(),
+            "".to_string(),
+            "- four spaces".to_string(),
+            "    - nested".to_string(),
+            "    - sibling".to_string(),
+            "- back out".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::OrderedList {
+                    start: 1,
+                    list: vec![
+                        ListItem {
+                            checked: None,
+                            lines: vec![plain("Install")],
+                            children: vec![HTMLElement::UnorderedList {
+                                list: vec![
+                                    ListItem {
+                                        checked: None,
+                                        lines: vec![vec![code("brew install tmux")]],
+                                        children: vec![HTMLElement::OrderedList {
+                                            start: 1,
+                                            list: vec![item(vec![
+                                                text_node("check "),
+                                                code("tmux -V")
+                                            ])],
+                                            tight: true
+                                        }]
+                                    },
+                                    item(vec![code("apt install tmux")]),
+                                ],
+                                tight: true
+                            }]
+                        },
+                        item(plain("Configure")),
+                    ],
+                    tight: true
+                },
+                HTMLElement::UnorderedList {
+                    list: vec![
+                        ListItem {
+                            checked: None,
+                            lines: vec![plain("four spaces")],
+                            children: vec![HTMLElement::UnorderedList {
+                                list: vec![item(plain("nested")), item(plain("sibling"))],
+                                tight: true
+                            }]
+                        },
+                        item(plain("back out")),
+                    ],
+                    tight: true
+                },
+            ]
+        );
+    }
+
+    /// Test that a list ends at the first line which is not a list item.
+    #[test]
+    fn test_list_followed_by_region() {
+        let text = vec![
+            "- item".to_string(),
+            "# Header".to_string(),
+            "1.5 is not a list item".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::UnorderedList {
+                    list: vec![item(plain("item"))],
+                    tight: true
+                },
+                HTMLElement::Header {
+                    level: 1,
+                    content: "Header".to_string()
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("1.5 is not a list item")]
+                },
+            ]
+        );
+    }
+
+    /// Test that a list item can hold a paragraph followed by an indented code block.
+    #[test]
+    fn test_list_item_with_code() {
+        let text = vec![
+            "1. Squash the commits.".to_string(),
+            "   ```sh".to_string(),
+            "   git reset --soft main".to_string(),
+            "".to_string(),
+            "   git commit".to_string(),
+            "   ```".to_string(),
+            "2. Force push.".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![HTMLElement::OrderedList {
+                start: 1,
+                list: vec![
+                    ListItem {
+                        checked: None,
+                        lines: vec![plain("Squash the commits.")],
+                        children: vec![HTMLElement::Code {
+                            language: "sh".to_string(),
+                            title: None,
+                            highlight: vec![],
+                            line_numbers: false,
+                            code: vec![
+                                "git reset --soft main".to_string(),
+                                "".to_string(),
+                                "git commit".to_string(),
+                            ]
+                        }]
+                    },
+                    item(plain("Force push.")),
+                ],
+                tight: true
+            }]
+        );
+    }
+
+    /// Test that blank lines between items or the elements inside them make a list loose.
+    #[test]
+    fn test_loose_lists() {
+        let text = vec![
+            "- first".to_string(),
+            "".to_string(),
+            "- second".to_string(),
+            "".to_string(),
+            "1. Paragraph one".to_string(),
+            "   continued.".to_string(),
+            "".to_string(),
+            "   Paragraph two.".to_string(),
+            "".to_string(),
+            "After the list.".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::UnorderedList {
+                    list: vec![item(plain("first")), item(plain("second"))],
+                    tight: false
+                },
+                HTMLElement::OrderedList {
+                    start: 1,
+                    list: vec![ListItem {
+                        checked: None,
+                        lines: vec![plain("Paragraph one"), plain("continued.")],
+                        children: vec![HTMLElement::Paragraph {
+                            lines: vec![plain("Paragraph two.")]
+                        }]
+                    }],
+                    tight: false
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("After the list.")]
+                },
+            ]
+        );
+    }
+
+    /// Test that blank lines inside a child list do not make the parent list loose.
+    #[test]
+    fn test_loose_child_list() {
+        let text = vec![
+            "- parent".to_string(),
+            "  - child one".to_string(),
+            "".to_string(),
+            "  - child two".to_string(),
+            "- sibling".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![HTMLElement::UnorderedList {
+                list: vec![
+                    ListItem {
+                        checked: None,
+                        lines: vec![plain("parent")],
+                        children: vec![HTMLElement::UnorderedList {
+                            list: vec![item(plain("child one")), item(plain("child two"))],
+                            tight: false
+                        }]
+                    },
+                    item(plain("sibling")),
+                ],
+                tight: true
+            }]
+        );
+    }
+
+    /// Test that unordered list items can start with a task checkbox.
+    #[test]
+    fn test_task_list() {
+        let text = vec![
+            "- [x] install `tmux`".to_string(),
+            "- [ ] write `.tmux.conf`".to_string(),
+            "- [X]".to_string(),
+            "- [link](https://github.com/tmux/tmux)".to_string(),
+            "- [x]not a task".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![HTMLElement::UnorderedList {
+                list: vec![
+                    ListItem {
+                        checked: Some(true),
+                        lines: vec![vec![text_node("install "), code("tmux")]],
+                        children: vec![]
+                    },
+                    ListItem {
+                        checked: Some(false),
+                        lines: vec![vec![text_node("write "), code(".tmux.conf")]],
+                        children: vec![]
+                    },
+                    ListItem {
+                        checked: Some(true),
+                        lines: vec![],
+                        children: vec![]
+                    },
+                    item(vec![Inline::Link {
+                        href: "https://github.com/tmux/tmux".to_string(),
+                        title: None,
+                        label: plain("link")
+                    }]),
+                    item(plain("[x]not a task")),
+                ],
+                tight: true
+            }]
+        );
+    }
+
+    /// Test that the separator row sets the alignment of each column.
+    #[test]
+    fn test_table_alignment() {
+        let table = vec![
+            "| Shell | PowerShell | Seconds |   Notes   |".to_string(),
+            "| :---- | ---------- | ------: | :-------: |".to_string(),
+            "| `ls`  | `Get-Item` |     0.1 | alias     |".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&table),
+            vec![HTMLElement::Table {
+                headers: vec![
+                    plain("Shell"),
+                    plain("PowerShell"),
+                    plain("Seconds"),
+                    plain("Notes")
+                ],
+                alignment: vec![
+                    Some(Alignment::Left),
+                    None,
+                    Some(Alignment::Right),
+                    Some(Alignment::Center)
+                ],
+                rows: vec![vec![
+                    vec![code("ls")],
+                    vec![code("Get-Item")],
+                    plain("0.1"),
+                    plain("alias")
+                ]],
+            }]
+        );
+    }
+
+    /// Test that escaped pipes and pipes inside code spans do not separate cells.
+    #[test]
+    fn test_table_pipes_in_cells() {
+        let table = vec![
+            "| Syntax      | Meaning        |".to_string(),
+            "| ----------- | -------------- |".to_string(),
+            "| `a \\| b`    | pipe a into b  |".to_string(),
+            "| `a || b`    | b if a failed  |".to_string(),
+            "| \\|         | a literal pipe |".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&table),
+            vec![HTMLElement::Table {
+                headers: vec![plain("Syntax"), plain("Meaning")],
+                alignment: vec![None, None],
+                rows: vec![
+                    vec![vec![code("a | b")], plain("pipe a into b")],
+                    vec![vec![code("a || b")], plain("b if a failed")],
+                    vec![plain("|"), plain("a literal pipe")],
+                ],
+            }]
+        );
+    }
+
+    /// Test that a line starting with a pipe is a paragraph unless it is followed by a separator row with a cell for each header.
+    #[test]
+    fn test_table_without_separator() {
+        let text = vec![
+            "| a |".to_string(),
+            "more text".to_string(),
+            "".to_string(),
+            "| a |".to_string(),
+            "| b |".to_string(),
+            "".to_string(),
+            "| a |".to_string(),
+            "".to_string(),
+            "| a | b |".to_string(),
+            "| --- |".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Paragraph {
+                    lines: vec![plain("| a |"), plain("more text")]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("| a |"), plain("| b |")]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("| a |")]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("| a | b |"), plain("| --- |")]
+                },
+            ]
+        );
+    }
+
+    /// Test that rows are padded or truncated to the number of headers.
+    #[test]
+    fn test_table_ragged_rows() {
+        let table = vec![
+            "| one | two |".to_string(),
+            "| --- | --- |".to_string(),
+            "| 1 |".to_string(),
+            "| 1 | 2 | 3 |".to_string(),
+            "| without | trailing pipe".to_string(),
+            "After the table.".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&table),
+            vec![
+                HTMLElement::Table {
+                    headers: vec![plain("one"), plain("two")],
+                    alignment: vec![None, None],
+                    rows: vec![
+                        vec![plain("1"), vec![]],
+                        vec![plain("1"), plain("2")],
+                        vec![plain("without"), plain("trailing pipe")],
+                    ],
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("After the table.")]
+                },
+            ]
+        );
+    }
+
+    /// Test that ordered lists keep their starting number and can use `')'` after the number.
+    #[test]
+    fn test_ordered_list_start() {
+        let text = vec![
+            "3. continue from three".to_string(),
+            "4. four".to_string(),
+            "1) new list".to_string(),
+            "".to_string(),
+            "Text ending in the year".to_string(),
+            "2024. is still text".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::OrderedList {
+                    start: 3,
+                    list: vec![item(plain("continue from three")), item(plain("four"))],
+                    tight: true
+                },
+                HTMLElement::OrderedList {
+                    start: 1,
+                    list: vec![item(plain("new list"))],
+                    tight: true
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![
+                        plain("Text ending in the year"),
+                        plain("2024. is still text")
+                    ]
+                },
+            ]
+        );
+    }
+
+    /// Test horizontal rules, which take precedence over unordered list items.
+    #[test]
+    fn test_rule() {
+        let text = vec![
+            "---".to_string(),
+            "- item".to_string(),
+            "- - -".to_string(),
+            "Text".to_string(),
+            "***".to_string(),
+            " _ _ _ _".to_string(),
+            "-- not a rule".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Rule,
+                HTMLElement::UnorderedList {
+                    list: vec![item(plain("item"))],
+                    tight: true
+                },
+                HTMLElement::Rule,
+                HTMLElement::Paragraph {
+                    lines: vec![plain("Text")]
+                },
+                HTMLElement::Rule,
+                HTMLElement::Rule,
+                HTMLElement::Paragraph {
+                    lines: vec![plain("-- not a rule")]
+                },
+            ]
+        );
+    }
+
+    /// Test that an image on its own is a block, while an image inside text stays inline.
+    #[test]
+    fn test_image() {
+        let text = vec![
+            "![diagram](diagram.png \"Overview\")".to_string(),
+            "".to_string(),
+            "An icon ![icon](icon.svg) inline".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Image(Image {
+                    src: "diagram.png".to_string(),
+                    alt: "diagram".to_string(),
+                    title: Some("Overview".to_string()),
+                    width: None,
+                    height: None,
+                }),
+                HTMLElement::Paragraph {
+                    lines: vec![vec![
+                        text_node("An icon "),
+                        Inline::Image(Image {
+                            src: "icon.svg".to_string(),
+                            alt: "icon".to_string(),
+                            title: None,
+                            width: None,
+                            height: None
+                        }),
+                        text_node(" inline")
+                    ]]
+                },
+            ]
+        );
+    }
+
+    /// Test headers with closing hashes, and hashtags which are not headers.
+    #[test]
+    fn test_header_closing_hashes() {
+        let text = vec![
+            "## Header ##".to_string(),
+            "".to_string(),
+            "# C# #".to_string(),
+            "".to_string(),
+            "#hashtag".to_string(),
+            "".to_string(),
+            "####### too deep".to_string(),
+            "".to_string(),
+            "###".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Header {
+                    level: 2,
+                    content: "Header".to_string()
+                },
+                HTMLElement::Header {
+                    level: 1,
+                    content: "C#".to_string()
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("#hashtag")]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("####### too deep")]
+                },
+                HTMLElement::Header {
+                    level: 3,
+                    content: "".to_string()
+                },
+            ]
+        );
+    }
+
+    /// Test setext headers, which underline a paragraph instead of being a horizontal rule.
+    #[test]
+    fn test_setext_header() {
+        let text = vec![
+            "Title".to_string(),
+            "=====".to_string(),
+            "Subtitle over".to_string(),
+            "two lines".to_string(),
+            "---".to_string(),
+            "".to_string(),
+            "---".to_string(),
+            "Text".to_string(),
+            "- - -".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Header {
+                    level: 1,
+                    content: "Title".to_string()
+                },
+                HTMLElement::Header {
+                    level: 2,
+                    content: "Subtitle over two lines".to_string()
+                },
+                HTMLElement::Rule,
+                HTMLElement::Paragraph {
+                    lines: vec![plain("Text")]
+                },
+                HTMLElement::Rule,
+            ]
+        );
+    }
+
+    /// Test that code fences only close on a fence of the same character that is at least as long.
+    #[test]
+    fn test_code_fences() {
+        let text = vec![
+            "````md".to_string(),
+            "```py".to_string(),
+            "print()".to_string(),
+            "```".to_string(),
+            "`````".to_string(),
+            "~~~".to_string(),
+            "```".to_string(),
+            "~~~~".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Code {
+                    language: "md".to_string(),
+                    title: None,
+                    highlight: vec![],
+                    line_numbers: false,
+                    code: vec![
+                        "```py".to_string(),
+                        "print()".to_string(),
+                        "```".to_string()
+                    ]
+                },
+                HTMLElement::Code {
+                    language: "".to_string(),
+                    title: None,
+                    highlight: vec![],
+                    line_numbers: false,
+                    code: vec!["```".to_string()]
+                },
+            ]
+        );
+    }
+
+    /// Test that an indented fence removes its indentation from the code.
+    #[test]
+    fn test_indented_code_fence() {
+        let text = vec![
+            "  ```sh".to_string(),
+            "  echo one".to_string(),
+            "    echo two".to_string(),
+            "echo three".to_string(),
+            "   ```".to_string(),
+            "```not a fence```".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Code {
+                    language: "sh".to_string(),
+                    title: None,
+                    highlight: vec![],
+                    line_numbers: false,
+                    code: vec![
+                        "echo one".to_string(),
+                        "  echo two".to_string(),
+                        "echo three".to_string()
+                    ]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![vec![code("not a fence")]]
+                },
+            ]
+        );
+    }
+
+    /// Test that attributes of a code block's info string are parsed into fields.
+    #[test]
+    fn test_code_info_string() {
+        let text = vec![
+            r#"```cpp title="binary search.cpp" {3,5-7} showLineNumbers"#.to_string(),
+            "int main() {}".to_string(),
+            "```".to_string(),
+            "".to_string(),
+            "``` {2} unknown".to_string(),
+            "```".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Code {
+                    language: "cpp".to_string(),
+                    title: Some("binary search.cpp".to_string()),
+                    highlight: vec![
+                        LineRange { start: 3, end: 3 },
+                        LineRange { start: 5, end: 7 }
+                    ],
+                    line_numbers: true,
+                    code: vec!["int main() {}".to_string()]
+                },
+                HTMLElement::Code {
+                    language: "".to_string(),
+                    title: None,
+                    highlight: vec![LineRange { start: 2, end: 2 }],
+                    line_numbers: false,
+                    code: vec![]
+                },
+            ]
+        );
+    }
+
+    /// Test indented code blocks, which keep blank lines inside them but cannot interrupt a paragraph.
+    #[test]
+    fn test_indented_code() {
+        let text = vec![
+            "    fn main() {".to_string(),
+            "".to_string(),
+            "        println!();".to_string(),
+            "    }".to_string(),
+            "".to_string(),
+            "Text".to_string(),
+            "    continued".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Code {
+                    language: "".to_string(),
+                    title: None,
+                    highlight: vec![],
+                    line_numbers: false,
+                    code: vec![
+                        "fn main() {".to_string(),
+                        "".to_string(),
+                        "    println!();".to_string(),
+                        "}".to_string()
+                    ]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("Text"), plain("continued")]
+                },
+            ]
+        );
+    }
+
+    /// Test that footnotes are numbered by their first reference and collected at the end.
+    #[test]
+    fn test_footnotes() {
+        let text = vec![
+            "Second[^b], first[^a] and again[^b][^missing].".to_string(),
+            "".to_string(),
+            "[^a]: Unused until referenced.".to_string(),
+            "[^b]: The [docs](https://example.com).".to_string(),
+            "".to_string(),
+            "    More about b.".to_string(),
+            "[^unused]: Never referenced.".to_string(),
+            "```".to_string(),
+            "[^a]: not a definition".to_string(),
+            "```".to_string(),
+        ];
+        let reference = |label: &str, number, occurrence| Inline::FootnoteReference {
+            label: label.to_string(),
+            number,
+            occurrence,
+        };
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Paragraph {
+                    lines: vec![vec![
+                        text_node("Second"),
+                        reference("b", 1, 1),
+                        text_node(", first"),
+                        reference("a", 2, 1),
+                        text_node(" and again"),
+                        reference("b", 1, 2),
+                        text_node("[^missing]."),
+                    ]]
+                },
+                HTMLElement::Code {
+                    language: "".to_string(),
+                    title: None,
+                    highlight: vec![],
+                    line_numbers: false,
+                    code: vec!["[^a]: not a definition".to_string()]
+                },
+                HTMLElement::Footnotes {
+                    notes: vec![
+                        Footnote {
+                            number: 1,
+                            label: "b".to_string(),
+                            references: 2,
+                            children: vec![
+                                HTMLElement::Paragraph {
+                                    lines: vec![vec![
+                                        text_node("The "),
+                                        Inline::Link {
+                                            href: "https://example.com".to_string(),
+                                            title: None,
+                                            label: plain("docs")
+                                        },
+                                        text_node(".")
+                                    ]]
+                                },
+                                HTMLElement::Paragraph {
+                                    lines: vec![plain("More about b.")]
+                                },
+                            ]
+                        },
+                        Footnote {
+                            number: 2,
+                            label: "a".to_string(),
+                            references: 1,
+                            children: vec![HTMLElement::Paragraph {
+                                lines: vec![plain("Unused until referenced.")]
+                            }]
+                        },
+                    ]
+                },
+            ]
+        );
+    }
+
+    /// Test that footnotes referenced inside a callout are numbered like any other reference.
+    #[test]
+    fn test_footnotes_in_callout() {
+        let text = vec![
+            "> [!NOTE]".to_string(),
+            "> See [^n].".to_string(),
+            "".to_string(),
+            "[^n]: x".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Callout {
+                    kind: CalloutKind::Note,
+                    children: vec![HTMLElement::Paragraph {
+                        lines: vec![vec![
+                            text_node("See "),
+                            Inline::FootnoteReference {
+                                label: "n".to_string(),
+                                number: 1,
+                                occurrence: 1
+                            },
+                            text_node("."),
+                        ]]
+                    }]
+                },
+                HTMLElement::Footnotes {
+                    notes: vec![Footnote {
+                        number: 1,
+                        label: "n".to_string(),
+                        references: 1,
+                        children: vec![HTMLElement::Paragraph {
+                            lines: vec![plain("x")]
+                        }]
+                    }]
+                },
+            ]
+        );
+    }
+
+    /// Test that link definitions are removed and references to them become links.
+    #[test]
+    fn test_link_references() {
+        let text = vec![
+            "Read the [book][rust] or [Docs].".to_string(),
+            "".to_string(),
+            "[rust]: https://doc.rust-lang.org/book \"The Book\"".to_string(),
+            "  [docs]: <https://docs.rs>".to_string(),
+            "- see [docs] and [missing][]".to_string(),
+        ];
+        let link = |href: &str, title: Option<&str>, label: &str| Inline::Link {
+            href: href.to_string(),
+            title: title.map(str::to_string),
+            label: plain(label),
+        };
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Paragraph {
+                    lines: vec![vec![
+                        text_node("Read the "),
+                        link("https://doc.rust-lang.org/book", Some("The Book"), "book"),
+                        text_node(" or "),
+                        link("https://docs.rs", None, "Docs"),
+                        text_node("."),
+                    ]]
+                },
+                HTMLElement::UnorderedList {
+                    list: vec![ListItem {
+                        checked: None,
+                        lines: vec![vec![
+                            text_node("see "),
+                            link("https://docs.rs", None, "docs"),
+                            text_node(" and [missing][]"),
+                        ]],
+                        children: vec![]
+                    }],
+                    tight: true
+                },
+            ]
+        );
+    }
+
+    /// Test that references inside link labels and blockquotes are resolved, so the output can be serialised.
+    #[test]
+    fn test_link_references_nested() {
+        let text = vec![
+            "[arr[0]](https://x) and [see [docs] here](http://x)".to_string(),
+            "".to_string(),
+            "> see [docs]".to_string(),
+            "".to_string(),
+            "[docs]: https://docs.rs".to_string(),
+        ];
+        let link = |href: &str, label: &str| Inline::Link {
+            href: href.to_string(),
+            title: None,
+            label: plain(label),
+        };
+        let elements = parse_markdown(&text);
+        assert_eq!(
+            elements,
+            vec![
+                HTMLElement::Paragraph {
+                    lines: vec![vec![
+                        link("https://x", "arr[0]"),
+                        text_node(" and "),
+                        link("http://x", "see docs here"),
+                    ]]
+                },
+                HTMLElement::Blockquote {
+                    children: vec![HTMLElement::Paragraph {
+                        lines: vec![vec![text_node("see "), link("https://docs.rs", "docs")]]
+                    }]
+                },
+            ]
+        );
+        serde_json::to_string(&elements).expect("could not serialise parsed elements");
+    }
+
+    /// Test that definitions inside blockquotes are removed, but definition-like lines continuing a paragraph are kept.
+    #[test]
+    fn test_definitions_scope() {
+        let text = vec![
+            "> Read [docs].".to_string(),
+            ">".to_string(),
+            "> [docs]: https://docs.rs".to_string(),
+            "> > [^note]: Quoted note.".to_string(),
+            "".to_string(),
+            "Arrays are indexed like".to_string(),
+            "[i]: /not-a-definition".to_string(),
+            "".to_string(),
+            "> quoted".to_string(),
+            "> [q]: /kept".to_string(),
+            "".to_string(),
+            "Cite[^note].".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Blockquote {
+                    children: vec![HTMLElement::Paragraph {
+                        lines: vec![vec![
+                            text_node("Read "),
+                            Inline::Link {
+                                href: "https://docs.rs".to_string(),
+                                title: None,
+                                label: plain("docs")
+                            },
+                            text_node("."),
+                        ]]
+                    }]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![
+                        plain("Arrays are indexed like"),
+                        plain("[i]: /not-a-definition")
+                    ]
+                },
+                HTMLElement::Blockquote {
+                    children: vec![HTMLElement::Paragraph {
+                        lines: vec![plain("quoted"), plain("[q]: /kept")]
+                    }]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![vec![
+                        text_node("Cite"),
+                        Inline::FootnoteReference {
+                            label: "note".to_string(),
+                            number: 1,
+                            occurrence: 1
+                        },
+                        text_node("."),
+                    ]]
+                },
+                HTMLElement::Footnotes {
+                    notes: vec![Footnote {
+                        number: 1,
+                        label: "note".to_string(),
+                        references: 1,
+                        children: vec![HTMLElement::Paragraph {
+                            lines: vec![plain("Quoted note.")]
+                        }]
+                    }]
+                },
+            ]
+        );
+    }
+
+    /// Test HTML blocks, which are sanitised and end at a blank line or their closing text.
+    #[test]
+    fn test_html_blocks() {
+        let text = vec![
+            "<details open onclick=\"steal()\">".to_string(),
+            "<summary>More</summary>".to_string(),
+            "".to_string(),
+            "<pre>".to_string(),
+            "".to_string(),
+            "</pre>".to_string(),
+            "<!-- hidden".to_string(),
+            "comment -->".to_string(),
+            "Text".to_string(),
+            "<kbd>Ctrl</kbd>".to_string(),
+        ];
+        let html = |html: &str| Inline::Html {
+            html: html.to_string(),
+        };
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::HtmlBlock {
+                    html: "<details open>\n<summary>More</summary>".to_string()
+                },
+                HTMLElement::HtmlBlock {
+                    html: "<pre>\n\n</pre>".to_string()
+                },
+                HTMLElement::HtmlBlock {
+                    html: "".to_string()
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![
+                        plain("Text"),
+                        vec![html("<kbd>"), text_node("Ctrl"), html("</kbd>")]
+                    ]
+                },
+            ]
+        );
+    }
+
+    /// Test that a custom sanitizer controls which tags are allowed, and that other inline tags are kept as plain text.
+    #[test]
+    fn test_custom_sanitizer() {
+        let text =
+            vec!["Press <kbd>Q</kbd> or <script>alert(1)</script> for a Vec<int>".to_string()];
+        let html = |html: &str| Inline::Html {
+            html: html.to_string(),
+        };
+        assert_eq!(
+            parse_markdown_with_sanitizer(&text, &HtmlSanitizer::new()),
+            vec![HTMLElement::Paragraph {
+                lines: vec![plain(
+                    "Press <kbd>Q</kbd> or <script>alert(1)</script> for a Vec<int>"
+                )]
+            }]
+        );
+        assert_eq!(
+            parse_markdown_with_sanitizer(&text, &HtmlSanitizer::new().allow("kbd", &[])),
+            vec![HTMLElement::Paragraph {
+                lines: vec![vec![
+                    text_node("Press "),
+                    html("<kbd>"),
+                    text_node("Q"),
+                    html("</kbd>"),
+                    text_node(" or <script>alert(1)</script> for a Vec<int>"),
+                ]]
+            }]
+        );
+    }
+
+    /// Test that HTML inside a blockquote is sanitised once, by the given sanitizer.
+    #[test]
+    fn test_custom_sanitizer_in_blockquote() {
+        let text =
+            vec![r#"> <iframe src="https://example.com/?a=1&amp;b=2"></iframe>"#.to_string()];
+        let sanitizer = HtmlSanitizer::new().allow("iframe", &["src"]);
+        let html = |html: &str| Inline::Html {
+            html: html.to_string(),
+        };
+        assert_eq!(
+            parse_markdown_with_sanitizer(&text, &sanitizer),
+            vec![HTMLElement::Blockquote {
+                children: vec![HTMLElement::Paragraph {
+                    lines: vec![vec![
+                        html(r#"<iframe src="https://example.com/?a=1&amp;b=2">"#),
+                        html("</iframe>"),
+                    ]]
+                }]
+            }]
+        );
+    }
+
+    /// Test collapsible sections, which can be nested and hold any element.
+    #[test]
+    fn test_details() {
+        let text = vec![
+            "Intro".to_string(),
+            ":::details Key **tables**".to_string(),
+            "- prefix".to_string(),
+            "".to_string(),
+            "::: details".to_string(),
+            "```md".to_string(),
+            ":::".to_string(),
+            "```".to_string(),
+            ":::".to_string(),
+            ":::".to_string(),
+            "Outro".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Paragraph {
+                    lines: vec![plain("Intro")]
+                },
+                HTMLElement::Details {
+                    summary: vec![
+                        text_node("Key "),
+                        Inline::Strong {
+                            children: plain("tables")
+                        }
+                    ],
+                    children: vec![
+                        HTMLElement::UnorderedList {
+                            list: vec![item(plain("prefix"))],
+                            tight: true
+                        },
+                        HTMLElement::Details {
+                            summary: vec![],
+                            children: vec![HTMLElement::Code {
+                                language: "md".to_string(),
+                                title: None,
+                                highlight: vec![],
+                                line_numbers: false,
+                                code: vec![":::".to_string()]
+                            }]
+                        },
+                    ]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("Outro")]
+                },
+            ]
+        );
+    }
+
+    /// Test definition lists, where a term can have several definitions and a definition can continue on indented lines.
+    #[test]
+    fn test_definition_list() {
+        let paragraph = |line: Vec<Inline>| HTMLElement::Paragraph { lines: vec![line] };
+        let text = vec![
+            "Intro".to_string(),
+            "`ls`".to_string(),
+            ": list files".to_string(),
+            ": list **directories**".to_string(),
+            "".to_string(),
+            "  - `-a` for hidden files".to_string(),
+            "".to_string(),
+            "`cd`".to_string(),
+            ": change directory".to_string(),
+            "Outro".to_string(),
+            "continues".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                paragraph(plain("Intro")),
+                HTMLElement::DefinitionList {
+                    items: vec![
+                        DefinitionItem {
+                            term: vec![code("ls")],
+                            definitions: vec![
+                                vec![paragraph(plain("list files"))],
+                                vec![
+                                    paragraph(vec![
+                                        text_node("list "),
+                                        Inline::Strong {
+                                            children: plain("directories")
+                                        }
+                                    ]),
+                                    HTMLElement::UnorderedList {
+                                        list: vec![item(vec![
+                                            code("-a"),
+                                            text_node(" for hidden files")
+                                        ])],
+                                        tight: true
+                                    },
+                                ],
+                            ]
+                        },
+                        DefinitionItem {
+                            term: vec![code("cd")],
+                            definitions: vec![vec![paragraph(plain("change directory"))]]
+                        },
+                    ]
+                },
+                HTMLElement::Paragraph {
+                    lines: vec![plain("Outro"), plain("continues")]
+                },
+            ]
+        );
+    }
+
+    /// Test display math blocks, which are kept verbatim and can interrupt a paragraph.
+    #[test]
+    fn test_math() {
+        let math = |tex: &str| HTMLElement::Math {
+            display: true,
+            tex: tex.to_string(),
+        };
+        let text = vec![
+            "The invariant holds".to_string(),
+            "$$".to_string(),
+            r"  \forall i < lo, a_i < x".to_string(),
+            "".to_string(),
+            "  **hi**".to_string(),
+            "$$".to_string(),
+            r"$$ \begin{cases}".to_string(),
+            r"0 \\ 1 \end{cases} $$".to_string(),
+            "$$lo = hi$$".to_string(),
+            "$$x$$ is also inline".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Paragraph {
+                    lines: vec![plain("The invariant holds")]
+                },
+                math("  \\forall i < lo, a_i < x\n\n  **hi**"),
+                math("\\begin{cases}\n0 \\\\ 1 \\end{cases} "),
+                math("lo = hi"),
+                HTMLElement::Paragraph {
+                    lines: vec![vec![
+                        Inline::Math {
+                            display: true,
+                            tex: "x".to_string()
+                        },
+                        text_node(" is also inline")
+                    ]]
+                },
+            ]
+        );
+    }
+
+    /// Test that code blocks in a diagram language become diagrams.
+    #[test]
+    fn test_diagram() {
+        let text = vec![
+            "```mermaid".to_string(),
+            "gitGraph".to_string(),
+            "   commit".to_string(),
+            "```".to_string(),
+            "```dot title=\"upstream\"".to_string(),
+            "digraph { main -> feature }".to_string(),
+            "```".to_string(),
+        ];
+        assert_eq!(
+            parse_markdown(&text),
+            vec![
+                HTMLElement::Diagram {
+                    kind: DiagramKind::Mermaid,
+                    source: "gitGraph\n   commit".to_string(),
+                    svg: None
+                },
+                HTMLElement::Diagram {
+                    kind: DiagramKind::Dot,
+                    source: "digraph { main -> feature }".to_string(),
+                    svg: None
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,94 @@
+import HyperLink from "./components/HyperLink";
+
+/**
+ * Render inline elements which have already been parsed by the parser.
+ * Each element follows the Inline section of the parser JSON schema.
+ */
+
+/**
+ * Create the JSX for one line of inline elements.
+ * The content should be wrapped in a parent like a <p> or a <div>.
+ * @param {Object[]} inlines The inline elements of the line.
+ * @param {String} elementKey The key from the parent for React-rendering management.
+ * @returns A list of JSX elements where some are strings. This can be directly injected into a parent element eg <p>{genInline(line, key)}</p>
+ */
+export default function genInline(inlines, elementKey) {
+  return inlines.map((inline, index) =>
+    genOneInline(inline, `${elementKey}-inline-${index}`)
+  );
+}
+
+/**
+ * Create the JSX for a single inline element, including any inline elements nested inside it.
+ * @param {Object} inline The inline element.
+ * @param {String} elementKey The key for React-rendering management.
+ * @returns The JSX element, or a String for plain text.
+ */
+function genOneInline(inline, elementKey) {
+  switch (inline.type) {
+    case "Text":
+      return inline.text;
+    case "Code":
+      return (
+        <code className="bg-[#eeeeee] rounded-sm p-1" key={elementKey}>
+          {inline.code}
+        </code>
+      );
+    // math is shown as its TeX source
+    case "Math":
+      return (
+        <code className="bg-[#eeeeee] rounded-sm p-1" key={elementKey}>
+          {inline.tex}
+        </code>
+      );
+    case "Strong":
+      return <b key={elementKey}>{genInline(inline.children, elementKey)}</b>;
+    case "Emphasis":
+      return <i key={elementKey}>{genInline(inline.children, elementKey)}</i>;
+    case "Strikethrough":
+      return <s key={elementKey}>{genInline(inline.children, elementKey)}</s>;
+    case "Mark":
+      return (
+        <mark key={elementKey}>{genInline(inline.children, elementKey)}</mark>
+      );
+    case "Link":
+      return (
+        <HyperLink
+          url={inline.href}
+          description={genInline(inline.label, elementKey)}
+          key={elementKey}
+        />
+      );
+    case "Image": {
+      const { src, alt, title, width, height } = inline;
+      return (
+        <img
+          src={src}
+          alt={alt}
+          title={title ?? undefined}
+          width={width ?? undefined}
+          height={height ?? undefined}
+          key={elementKey}
+        />
+      );
+    }
+    // ids link each reference and its footnote to each other
+    case "FootnoteReference": {
+      const { number, occurrence } = inline;
+      return (
+        <sup id={`fnref-${number}-${occurrence}`} key={elementKey}>
+          <a className="text-[#0969da]" href={`#fn-${number}`}>
+            [{number}]
+          </a>
+        </sup>
+      );
+    }
+    // each tag is a separate element, which cannot be split across React elements, so only the text between tags is shown
+    case "Html":
+      return null;
+    default: {
+      console.log(`ERROR: unsupported inline type ${inline.type}`);
+      return null;
+    }
+  }
+}
@@ -1,7 +1,8 @@
+import { Fragment } from "react";
 import { useParams } from "react-router-dom";
 import { URLtoBlog } from "../blogToURL";
 import { getBlog } from "../unpack";
-import parseOneLine from "../parser";
+import genInline from "../inline";
 
 export default function Blog() {
   const { lang, title } = useParams();
@@ -57,6 +58,17 @@ const genElementKey = (urlTitle, index) => {
   return `${urlTitle}-${index}`;
 };
 
+/**
+ * The border colour of each kind of callout, matching the colours used by GitHub.
+ */
+const calloutBorders = {
+  Note: "border-[#0969da]",
+  Tip: "border-[#1a7f37]",
+  Important: "border-[#8250df]",
+  Warning: "border-[#9a6700]",
+  Caution: "border-[#cf222e]",
+};
+
 /**
  * Create all the JSX Element from given HTML data as JSON.
  * @param {Object} htmlData The structured JSON HTML data which follows the parser JSON schema.
@@ -107,51 +119,64 @@ const genHTML = (htmlData, elementKey) => {
       }
     }
     case "Code": {
-      const { code } = htmlData;
+      const { title, highlight, line_numbers, code } = htmlData;
+      const subKey = `${elementKey}-code_block`;
+      // highlighted line ranges are inclusive and numbered from 1
+      const isHighlighted = (number) =>
+        highlight.some(({ start, end }) => start <= number && number <= end);
       return (
         // this div is needed to ensure code blocks appear on their own lines even if there is room for both to appear on the same one
-        <div key={`${elementKey}-code_block`}>
+        <div key={subKey}>
+          {title !== null && <div className="mt-2 font-bold">{title}</div>}
           {/* inline-block allows the <pre> to grow as big as the <code> */}
           <pre className="inline-block border-[2px] my-2 border-black p-2 overflow-x-auto">
-            <code className="w-max">{code.join("\n")}</code>
+            <code className="w-max">
+              {code.map((line, index) => {
+                const number = index + 1;
+                return (
+                  <span
+                    className={`block ${isHighlighted(number) ? "bg-[#fff8c5]" : ""}`}
+                    key={`${subKey}-line-${index}`}
+                  >
+                    {line_numbers && (
+                      <span className="inline-block w-[3ch] mr-2 text-right text-[#888888] select-none">
+                        {number}
+                      </span>
+                    )}
+                    {/* a space keeps the height of blank lines */}
+                    {line === "" ? " " : line}
+                  </span>
+                );
+              })}
+            </code>
           </pre>
         </div>
       );
     }
     case "OrderedList": {
-      const { list } = htmlData;
-      const subKey = `${elementKey}-unordered_list`;
+      const { start, list } = htmlData;
+      const subKey = `${elementKey}-ordered_list`;
       return (
-        <ol key={subKey}>
-          {list.map((li, index) => (
-            <li
-              className="list-inside list-decimal"
-              key={`${subKey}-item-${index}`}
-            >
-              {parseOneLine(li, subKey)}
-            </li>
-          ))}
+        <ol start={start} key={subKey}>
+          {list.map((li, index) =>
+            genListItem(li, "list-decimal", `${subKey}-item-${index}`)
+          )}
         </ol>
       );
     }
     case "UnorderedList": {
       const { list } = htmlData;
-      const subKey = `${elementKey}-ordered_list`;
+      const subKey = `${elementKey}-unordered_list`;
       return (
         <ul key={subKey}>
-          {list.map((li, index) => (
-            <li
-              className="list-inside list-disc"
-              key={`${subKey}-item-${index}`}
-            >
-              {parseOneLine(li, subKey)}
-            </li>
-          ))}
+          {list.map((li, index) =>
+            genListItem(li, "list-disc", `${subKey}-item-${index}`)
+          )}
         </ul>
       );
     }
     case "Table": {
-      const { headers, rows } = htmlData;
+      const { headers, alignment, rows } = htmlData;
       const subKey = `${elementKey}-table`;
       return (
         <div
@@ -165,11 +190,11 @@ const genHTML = (htmlData, elementKey) => {
                   const headerKey = `${subKey}-header-${index}`;
                   return (
                     <th
-                      className="bg-[#e2edff] p-1"
+                      className={`bg-[#e2edff] p-1 ${alignmentClass(alignment[index])}`}
                       scope="col"
                       key={headerKey}
                     >
-                      {parseOneLine(header, subKey)}
+                      {genInline(header, headerKey)}
                     </th>
                   );
                 })}
@@ -183,8 +208,11 @@ const genHTML = (htmlData, elementKey) => {
                     {row.map((col, c) => {
                       const colKey = `${rowKey}-col-${c}`;
                       return (
-                        <td className="p-1" key={colKey}>
-                          {parseOneLine(col, colKey)}
+                        <td
+                          className={`p-1 ${alignmentClass(alignment[c])}`}
+                          key={colKey}
+                        >
+                          {genInline(col, colKey)}
                         </td>
                       );
                     })}
@@ -201,17 +229,251 @@ const genHTML = (htmlData, elementKey) => {
       const subKey = `${elementKey}-paragraph`;
       return (
         <div key={subKey}>
-          {lines.map((line, index) => (
-            <p className="wrap-break-word" key={`${subKey}-line-${index}`}>
-              {parseOneLine(line, subKey)}
-            </p>
-          ))}
+          {lines.map((line, index) => {
+            const lineKey = `${subKey}-line-${index}`;
+            return (
+              <p className="wrap-break-word" key={lineKey}>
+                {genInline(line, lineKey)}
+              </p>
+            );
+          })}
         </div>
       );
     }
+    // quoted elements are rendered recursively
+    case "Blockquote": {
+      const { children } = htmlData;
+      const subKey = `${elementKey}-blockquote`;
+      return (
+        <blockquote
+          className="border-l-4 border-[#cccccc] pl-3 my-2 text-[#555555]"
+          key={subKey}
+        >
+          {children.map((child, index) =>
+            genHTML(child, `${subKey}-child-${index}`)
+          )}
+        </blockquote>
+      );
+    }
+    case "Callout": {
+      const { kind, children } = htmlData;
+      const subKey = `${elementKey}-callout`;
+      return (
+        <div
+          className={`border-l-4 pl-3 my-2 ${calloutBorders[kind]}`}
+          key={subKey}
+        >
+          <p className="font-bold">{kind}</p>
+          {children.map((child, index) =>
+            genHTML(child, `${subKey}-child-${index}`)
+          )}
+        </div>
+      );
+    }
+    case "Rule":
+      return (
+        <hr
+          className="my-4 border-t-[2px] border-black"
+          key={`${elementKey}-rule`}
+        />
+      );
+    // a standalone image has the same fields as an inline image
+    case "Image": {
+      const subKey = `${elementKey}-image`;
+      return (
+        <div className="my-2" key={subKey}>
+          {genInline([htmlData], subKey)}
+        </div>
+      );
+    }
+    case "Footnotes": {
+      const { notes } = htmlData;
+      const subKey = `${elementKey}-footnotes`;
+      return (
+        <section
+          className="mt-6 pt-2 border-t-[2px] border-black text-[0.9em]"
+          key={subKey}
+        >
+          <ol>
+            {notes.map((note) =>
+              genFootnote(note, `${subKey}-note-${note.number}`)
+            )}
+          </ol>
+        </section>
+      );
+    }
+    // the section starts collapsed and its elements are rendered recursively
+    case "Details": {
+      const { summary, children } = htmlData;
+      const subKey = `${elementKey}-details`;
+      return (
+        <details className="my-2" key={subKey}>
+          <summary className="cursor-pointer">
+            {genInline(summary, `${subKey}-summary`)}
+          </summary>
+          <div className="pl-4">
+            {children.map((child, index) =>
+              genHTML(child, `${subKey}-child-${index}`)
+            )}
+          </div>
+        </details>
+      );
+    }
+    case "DefinitionList": {
+      const { items } = htmlData;
+      const subKey = `${elementKey}-definition_list`;
+      return (
+        <dl className="my-2" key={subKey}>
+          {items.map((item, i) => {
+            const itemKey = `${subKey}-item-${i}`;
+            return (
+              <Fragment key={itemKey}>
+                <dt className="font-bold">
+                  {genInline(item.term, `${itemKey}-term`)}
+                </dt>
+                {item.definitions.map((definition, d) => {
+                  const definitionKey = `${itemKey}-definition-${d}`;
+                  return (
+                    <dd className="pl-6" key={definitionKey}>
+                      {definition.map((child, index) =>
+                        genHTML(child, `${definitionKey}-child-${index}`)
+                      )}
+                    </dd>
+                  );
+                })}
+              </Fragment>
+            );
+          })}
+        </dl>
+      );
+    }
+    // math is shown as its TeX source, like inline math
+    case "Math": {
+      const { tex } = htmlData;
+      return (
+        <div className="flex justify-center" key={`${elementKey}-math`}>
+          <pre className="inline-block bg-[#eeeeee] rounded-sm my-2 p-2 overflow-x-auto">
+            <code>{tex}</code>
+          </pre>
+        </div>
+      );
+    }
+    // the svg is rendered by Graphviz when the blogs are dumped, otherwise the diagram's source is shown
+    case "Diagram": {
+      const { kind, source, svg } = htmlData;
+      const subKey = `${elementKey}-diagram`;
+      if (svg !== null) {
+        return (
+          <div
+            className="my-2 flex justify-center overflow-x-auto"
+            dangerouslySetInnerHTML={{ __html: svg }}
+            key={subKey}
+          />
+        );
+      }
+      return (
+        <div key={subKey}>
+          <pre className="inline-block border-[2px] my-2 border-black p-2 overflow-x-auto">
+            <code className="w-max" data-diagram={kind.toLowerCase()}>
+              {source}
+            </code>
+          </pre>
+        </div>
+      );
+    }
+    // the html has already been sanitised by the parser, so it is safe to inject
+    case "HtmlBlock": {
+      const { html } = htmlData;
+      return (
+        <div
+          className="my-2"
+          dangerouslySetInnerHTML={{ __html: html }}
+          key={`${elementKey}-html_block`}
+        />
+      );
+    }
     default: {
       console.log(`ERROR: unsupported HTML type ${htmlData.type}`);
       return <></>;
     }
   }
 };
+
+/**
+ * Get the class which aligns the text of a table column.
+ * @param {String} alignment The column's alignment, which is "Left", "Center", "Right" or null for the default.
+ * @returns A String of the Tailwind class, which is empty for the default alignment.
+ */
+const alignmentClass = (alignment) => {
+  switch (alignment) {
+    case "Left":
+      return "text-left";
+    case "Center":
+      return "text-center";
+    case "Right":
+      return "text-right";
+    default:
+      return "";
+  }
+};
+
+/**
+ * Create the JSX for an item of an ordered or unordered list.
+ * @param {Object} item The list item, which follows the List Item section of the parser JSON schema.
+ * @param {String} markerClass The class which styles the item's list marker.
+ * @param {String} itemKey The key for React-rendering management.
+ * @returns The <li> element holding the item's leading lines followed by its nested elements.
+ */
+const genListItem = (item, markerClass, itemKey) => {
+  const { checked, lines, children } = item;
+  return (
+    <li className={`list-inside ${markerClass}`} key={itemKey}>
+      {checked !== null && (
+        <input className="mr-1" type="checkbox" checked={checked} disabled />
+      )}
+      {lines.map((line, index) => {
+        const lineKey = `${itemKey}-line-${index}`;
+        return (
+          <Fragment key={lineKey}>
+            {index > 0 && <br />}
+            {genInline(line, lineKey)}
+          </Fragment>
+        );
+      })}
+      {children.map((child, index) =>
+        genHTML(child, `${itemKey}-child-${index}`)
+      )}
+    </li>
+  );
+};
+
+/**
+ * Create the JSX for a footnote, followed by a link back to each of its references.
+ * The ids match those of the footnote references created by genInline.
+ * @param {Object} note The footnote, which follows the Footnote section of the parser JSON schema.
+ * @param {String} noteKey The key for React-rendering management.
+ * @returns The <li> element holding the footnote's number, elements and back-references.
+ */
+const genFootnote = (note, noteKey) => {
+  const { number, references, children } = note;
+  return (
+    <li className="flex gap-2" id={`fn-${number}`} key={noteKey}>
+      <span>[{number}]</span>
+      <div>
+        {children.map((child, index) =>
+          genHTML(child, `${noteKey}-child-${index}`)
+        )}
+        {Array.from({ length: references }, (_, index) => (
+          <a
+            className="ml-1 text-[#0969da]"
+            href={`#fnref-${number}-${index + 1}`}
+            aria-label={`Back to reference ${index + 1}`}
+            key={`${noteKey}-back-${index}`}
+          >
+            ↩
+          </a>
+        ))}
+      </div>
+    </li>
+  );
+};